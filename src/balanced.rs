@@ -0,0 +1,433 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::Hash,
+    iter::Sum,
+    marker::PhantomData,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+pub trait BalancedRadix: Clone + Copy + fmt::Debug + Eq + Hash + Default {
+    const NAME: &'static str;
+    const BASE: i8;
+    const ALPHABET: &'static str;
+
+    fn digit_value(c: char) -> Option<i8> {
+        Self::ALPHABET
+            .chars()
+            .position(|d| d == c)
+            .map(|position| position as i8 - Self::BASE / 2)
+    }
+
+    fn digit_char(value: i8) -> char {
+        Self::ALPHABET
+            .chars()
+            .nth((value + Self::BASE / 2) as usize)
+            .unwrap()
+    }
+}
+
+pub fn check_radix<R: BalancedRadix>() -> Result<(), String> {
+    match radix_error(R::BASE, R::ALPHABET) {
+        None => Ok(()),
+        Some(RadixError::Base) => Err(format!("Base of {} must be odd and at least 3", R::NAME)),
+        Some(RadixError::AlphabetLength(digits)) => Err(format!(
+            "Alphabet of {} has {} digits, expected {}",
+            R::NAME,
+            digits,
+            R::BASE
+        )),
+        Some(RadixError::DuplicateDigits) => {
+            Err(format!("Alphabet of {} has duplicate digits", R::NAME))
+        }
+    }
+}
+
+enum RadixError {
+    Base,
+    AlphabetLength(usize),
+    DuplicateDigits,
+}
+
+const fn radix_error(base: i8, alphabet: &str) -> Option<RadixError> {
+    if base < 3 || base % 2 == 0 {
+        return Some(RadixError::Base);
+    }
+    let bytes = alphabet.as_bytes();
+    let mut digits = 0;
+    let mut start = 0;
+    while start < bytes.len() {
+        let len = utf8_len(bytes[start]);
+        let mut other = start + len;
+        while other < bytes.len() {
+            let other_len = utf8_len(bytes[other]);
+            if other_len == len {
+                let mut i = 0;
+                while i < len && bytes[start + i] == bytes[other + i] {
+                    i += 1;
+                }
+                if i == len {
+                    return Some(RadixError::DuplicateDigits);
+                }
+            }
+            other += other_len;
+        }
+        digits += 1;
+        start += len;
+    }
+    if digits != base as usize {
+        return Some(RadixError::AlphabetLength(digits));
+    }
+    None
+}
+
+const fn utf8_len(first_byte: u8) -> usize {
+    match first_byte {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Ternary;
+
+impl BalancedRadix for Ternary {
+    const NAME: &'static str = "balanced ternary";
+    const BASE: i8 = 3;
+    const ALPHABET: &'static str = "-0+";
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Balanced<R: BalancedRadix> {
+    digits: Vec<i8>,
+    radix: PhantomData<R>,
+}
+
+impl<R: BalancedRadix> Balanced<R> {
+    // Fails to compile when a Balanced<R> is built from an invalid radix.
+    const VALID_RADIX: () = assert!(
+        radix_error(R::BASE, R::ALPHABET).is_none(),
+        "Invalid balanced radix, see check_radix"
+    );
+
+    pub fn zero() -> Balanced<R> {
+        Balanced::from_digits(vec![])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn signum(&self) -> i8 {
+        self.digits.last().map_or(0, |d| d.signum())
+    }
+
+    pub fn to_i128(&self) -> Option<i128> {
+        let base = R::BASE as i128;
+        let mut result: i128 = 0;
+        for digit in self.digits.iter().rev() {
+            result = result
+                .checked_mul(base - 1)?
+                .checked_add(result.checked_add(*digit as i128)?)?;
+        }
+        Some(result)
+    }
+
+    fn from_digits(mut digits: Vec<i8>) -> Balanced<R> {
+        let () = Self::VALID_RADIX;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Balanced {
+            digits,
+            radix: PhantomData,
+        }
+    }
+
+    fn add_digits(&self, other: &Balanced<R>) -> Balanced<R> {
+        let mut digits = vec![];
+        let mut carry = 0;
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            let sum = *self.digits.get(i).unwrap_or(&0) as i32
+                + *other.digits.get(i).unwrap_or(&0) as i32
+                + carry;
+            let (digit, next_carry) = balance::<R>(sum);
+            digits.push(digit);
+            carry = next_carry;
+        }
+        digits.push(carry as i8);
+        Balanced::from_digits(digits)
+    }
+
+    fn mul_digit(&self, multiplier: i8, shift: usize) -> Balanced<R> {
+        let mut digits = vec![0; shift];
+        let mut carry = 0;
+        for digit in self.digits.iter() {
+            let (digit, next_carry) = balance::<R>(*digit as i32 * multiplier as i32 + carry);
+            digits.push(digit);
+            carry = next_carry;
+        }
+        while carry != 0 {
+            let (digit, next_carry) = balance::<R>(carry);
+            digits.push(digit);
+            carry = next_carry;
+        }
+        Balanced::from_digits(digits)
+    }
+}
+
+fn balance<R: BalancedRadix>(value: i32) -> (i8, i32) {
+    let base = R::BASE as i32;
+    let carry = (value + base / 2).div_euclid(base);
+    ((value - base * carry) as i8, carry)
+}
+
+impl<R: BalancedRadix> Default for Balanced<R> {
+    fn default() -> Balanced<R> {
+        Balanced::zero()
+    }
+}
+
+impl<R: BalancedRadix> From<i128> for Balanced<R> {
+    fn from(mut value: i128) -> Balanced<R> {
+        let base = R::BASE as i128;
+        let mut digits = vec![];
+        while value != 0 {
+            let (digit, carry) = balance::<R>(value.rem_euclid(base) as i32);
+            digits.push(digit);
+            value = value.div_euclid(base) + carry as i128;
+        }
+        Balanced::from_digits(digits)
+    }
+}
+
+impl<R: BalancedRadix> FromStr for Balanced<R> {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Balanced<R>, String> {
+        if str.is_empty() {
+            return Err(format!("Empty {} number", R::NAME));
+        }
+        str.chars()
+            .rev()
+            .map(|c| {
+                R::digit_value(c).ok_or(format!("Unexpected char for {} digit: '{}'", R::NAME, c))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Balanced::from_digits)
+    }
+}
+
+impl<R: BalancedRadix> fmt::Display for Balanced<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "{}", R::digit_char(0));
+        }
+        for digit in self.digits.iter().rev() {
+            write!(f, "{}", R::digit_char(*digit))?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: BalancedRadix> Add for Balanced<R> {
+    type Output = Balanced<R>;
+
+    fn add(self, other: Balanced<R>) -> Balanced<R> {
+        self.add_digits(&other)
+    }
+}
+
+impl<R: BalancedRadix> Neg for Balanced<R> {
+    type Output = Balanced<R>;
+
+    fn neg(self) -> Balanced<R> {
+        Balanced::from_digits(self.digits.iter().map(|d| -d).collect())
+    }
+}
+
+impl<R: BalancedRadix> Sub for Balanced<R> {
+    type Output = Balanced<R>;
+
+    fn sub(self, other: Balanced<R>) -> Balanced<R> {
+        self.add_digits(&-other)
+    }
+}
+
+impl<R: BalancedRadix> Mul for Balanced<R> {
+    type Output = Balanced<R>;
+
+    fn mul(self, other: Balanced<R>) -> Balanced<R> {
+        other
+            .digits
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d != 0)
+            .fold(Balanced::zero(), |result, (shift, d)| {
+                result.add_digits(&self.mul_digit(*d, shift))
+            })
+    }
+}
+
+impl<R: BalancedRadix> Ord for Balanced<R> {
+    fn cmp(&self, other: &Balanced<R>) -> Ordering {
+        let negated_other = Balanced::from_digits(other.digits.iter().map(|d| -d).collect());
+        self.add_digits(&negated_other).signum().cmp(&0)
+    }
+}
+
+impl<R: BalancedRadix> PartialOrd for Balanced<R> {
+    fn partial_cmp(&self, other: &Balanced<R>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R: BalancedRadix> Sum for Balanced<R> {
+    fn sum<I: Iterator<Item = Balanced<R>>>(iter: I) -> Balanced<R> {
+        iter.fold(Balanced::zero(), |sum, b| sum.add_digits(&b))
+    }
+}
+
+impl<'a, R: BalancedRadix + 'a> Sum<&'a Balanced<R>> for Balanced<R> {
+    fn sum<I: Iterator<Item = &'a Balanced<R>>>(iter: I) -> Balanced<R> {
+        iter.fold(Balanced::zero(), |sum, b| sum.add_digits(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        balanced::{check_radix, Balanced, BalancedRadix, Ternary},
+        random::XorShift,
+        snafu::SnafuRadix,
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    struct Septenary;
+
+    impl BalancedRadix for Septenary {
+        const NAME: &'static str = "balanced septenary";
+        const BASE: i8 = 7;
+        const ALPHABET: &'static str = "cba0123";
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    struct Nonary;
+
+    impl BalancedRadix for Nonary {
+        const NAME: &'static str = "balanced nonary";
+        const BASE: i8 = 9;
+        const ALPHABET: &'static str = "DCBA01234";
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    struct Base99;
+
+    impl BalancedRadix for Base99 {
+        const NAME: &'static str = "balanced base 99";
+        const BASE: i8 = 99;
+        const ALPHABET: &'static str =
+            "ĀāĂăĄąĆćĈĉĊċČčĎďĐđĒēĔĕĖėĘęĚěĜĝĞğĠġĢģĤĥĦħĨĩĪīĬĭĮįİ0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMN";
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    struct Even;
+
+    impl BalancedRadix for Even {
+        const NAME: &'static str = "even";
+        const BASE: i8 = 4;
+        const ALPHABET: &'static str = "-01+";
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    struct Duplicated;
+
+    impl BalancedRadix for Duplicated {
+        const NAME: &'static str = "duplicated";
+        const BASE: i8 = 3;
+        const ALPHABET: &'static str = "-00";
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+    struct Short;
+
+    impl BalancedRadix for Short {
+        const NAME: &'static str = "short";
+        const BASE: i8 = 3;
+        const ALPHABET: &'static str = "-ā";
+    }
+
+    fn check_properties<R: BalancedRadix>(seed: u64) {
+        let mut rng = XorShift::new(seed);
+        let mut random_i128 = || -> i128 {
+            let magnitude = rng.next_in_range(0..64) as u32;
+            ((rng.next_u64() as i128) << 64 | rng.next_u64() as i128) >> (64 + magnitude)
+        };
+        for _ in 0..1000 {
+            let (a, b) = (random_i128(), random_i128());
+            let (ba, bb) = (Balanced::<R>::from(a), Balanced::<R>::from(b));
+            assert_eq!(Some(a), ba.to_i128());
+            assert_eq!(Ok(ba.clone()), ba.to_string().parse::<Balanced<R>>());
+            assert_eq!(Balanced::<R>::from(a + b), ba.clone() + bb.clone());
+            assert_eq!(Balanced::<R>::from(a - b), ba.clone() - bb.clone());
+            assert_eq!(Balanced::<R>::from(a * b), ba.clone() * bb.clone());
+            assert_eq!(Balanced::<R>::from(-a), -ba.clone());
+            assert_eq!(a.cmp(&b), ba.cmp(&bb));
+        }
+        for value in [0, 1, -1, i128::MIN, i128::MAX] {
+            assert_eq!(Some(value), Balanced::<R>::from(value).to_i128());
+        }
+    }
+
+    #[test]
+    fn test_radix_definitions() {
+        assert_eq!(Ok(()), check_radix::<Ternary>());
+        assert_eq!(Ok(()), check_radix::<SnafuRadix>());
+        assert_eq!(Ok(()), check_radix::<Septenary>());
+        assert_eq!(Ok(()), check_radix::<Nonary>());
+        assert_eq!(Ok(()), check_radix::<Base99>());
+        assert_eq!(
+            Err("Base of even must be odd and at least 3".to_string()),
+            check_radix::<Even>()
+        );
+        assert_eq!(
+            Err("Alphabet of duplicated has duplicate digits".to_string()),
+            check_radix::<Duplicated>()
+        );
+        assert_eq!(
+            Err("Alphabet of short has 2 digits, expected 3".to_string()),
+            check_radix::<Short>()
+        );
+        assert_eq!(Balanced::<Base99>::zero(), Balanced::default());
+    }
+
+    #[test]
+    fn test_balanced_ternary() {
+        for (decimal, ternary) in [(0, "0"), (1, "+"), (2, "+-"), (-5, "-++"), (8, "+0-")] {
+            assert_eq!(ternary, Balanced::<Ternary>::from(decimal).to_string());
+            assert_eq!(
+                Ok(Balanced::from(decimal)),
+                ternary.parse::<Balanced<Ternary>>()
+            );
+        }
+        assert_eq!(
+            Err("Unexpected char for balanced ternary digit: '1'".to_string()),
+            "+1".parse::<Balanced<Ternary>>()
+        );
+        assert_eq!("c", Balanced::<Septenary>::from(-3).to_string());
+        assert_eq!("1c", Balanced::<Septenary>::from(4).to_string());
+        assert_eq!("1D", Balanced::<Nonary>::from(5).to_string());
+    }
+
+    #[test]
+    fn test_random_round_trips() {
+        check_properties::<Ternary>(3);
+        check_properties::<SnafuRadix>(5);
+        check_properties::<Septenary>(7);
+        check_properties::<Nonary>(9);
+        check_properties::<Base99>(99);
+    }
+}
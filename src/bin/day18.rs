@@ -1,4 +1,9 @@
-use std::collections::{HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
 
 use aoc2022::input::read_lines;
 
@@ -19,9 +24,27 @@ fn main() {
         "Part 2: {:?}",
         connected_components_sizes(&open_sides).iter().max()
     );
+
+    let args = env::args().collect::<Vec<_>>();
+    if args.iter().any(|a| a == "--report") {
+        print_droplet_report(&droplet_report(&cubes, &open_sides));
+    }
+    let with_pockets = args.iter().any(|a| a == "--pockets");
+    for (flag, format) in [("--obj", MeshFormat::Obj), ("--stl", MeshFormat::Stl)] {
+        if let Some(path) = args
+            .iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+        {
+            match export_mesh(path, format, &cubes, &open_sides, with_pockets) {
+                Ok(()) => println!("Exported mesh to {}", path),
+                Err(err) => println!("Unable to export mesh to {}: {}", path, err),
+            }
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 struct Point {
     x: i32,
     y: i32,
@@ -54,14 +77,14 @@ impl Point {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 enum Dimension {
     X,
     Y,
     Z,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 enum Direction {
     Up,
     Down,
@@ -84,7 +107,7 @@ fn parse_point(str: &str) -> Result<Point, String> {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 struct CubeSide {
     center: Point,
     dimension: Dimension,
@@ -100,6 +123,36 @@ impl CubeSide {
         }
     }
 
+    fn outer_cube(&self) -> Point {
+        self.center.shift(self.dimension, self.direction)
+    }
+
+    fn normal(&self) -> Point {
+        Point { x: 0, y: 0, z: 0 }.shift(self.dimension, self.direction)
+    }
+
+    fn corners(&self) -> [Point; 4] {
+        let base = match self.direction {
+            Direction::Up => self.center.shift(self.dimension, Direction::Up),
+            Direction::Down => self.center,
+        };
+        let (u, v) = match self.dimension {
+            Dimension::X => (Dimension::Y, Dimension::Z),
+            Dimension::Y => (Dimension::Z, Dimension::X),
+            Dimension::Z => (Dimension::X, Dimension::Y),
+        };
+        let mut corners = [
+            base,
+            base.shift(u, Direction::Up),
+            base.shift(u, Direction::Up).shift(v, Direction::Up),
+            base.shift(v, Direction::Up),
+        ];
+        if self.direction == Direction::Down {
+            corners.reverse();
+        }
+        corners
+    }
+
     fn adjacent_sides(&self, open_sides: &HashSet<CubeSide>) -> Vec<CubeSide> {
         let mut result = vec![];
 
@@ -168,29 +221,301 @@ fn calculate_open_sides(centers: &[Point]) -> HashSet<CubeSide> {
     sides
 }
 
-fn connected_components_sizes(open_sides: &HashSet<CubeSide>) -> Vec<usize> {
+fn connected_components(open_sides: &HashSet<CubeSide>) -> Vec<HashSet<CubeSide>> {
     let mut not_visited: HashSet<&CubeSide> = HashSet::from_iter(open_sides.iter());
-    let mut connected_components_sizes: Vec<usize> = vec![];
+    let mut connected_components: Vec<HashSet<CubeSide>> = vec![];
 
     while let Some(connected_component_start) = not_visited.iter().cloned().next() {
-        connected_components_sizes.push(0);
-        let last_index = connected_components_sizes.len() - 1;
+        let mut connected_component = HashSet::new();
         let mut queue = VecDeque::new();
-        queue.push_back(connected_component_start.clone());
+        queue.push_back(*connected_component_start);
 
         while let Some(side) = queue.pop_front() {
             if !not_visited.contains(&side) {
                 continue;
             }
             not_visited.remove(&side);
-            connected_components_sizes[last_index] += 1;
-            for neighbor in side.adjacent_sides(&open_sides).iter() {
-                queue.push_back(neighbor.clone());
+            connected_component.insert(side);
+            for neighbor in side.adjacent_sides(open_sides).iter() {
+                queue.push_back(*neighbor);
+            }
+        }
+
+        connected_components.push(connected_component);
+    }
+
+    connected_components.sort_by_key(|c| std::cmp::Reverse(c.len()));
+    connected_components
+}
+
+fn connected_components_sizes(open_sides: &HashSet<CubeSide>) -> Vec<usize> {
+    connected_components(open_sides)
+        .iter()
+        .map(|c| c.len())
+        .collect()
+}
+
+fn outside_air(cubes: &HashSet<Point>) -> HashSet<Point> {
+    let mut outside = HashSet::new();
+    if cubes.is_empty() {
+        return outside;
+    }
+    let min = Point {
+        x: cubes.iter().map(|p| p.x).min().unwrap() - 1,
+        y: cubes.iter().map(|p| p.y).min().unwrap() - 1,
+        z: cubes.iter().map(|p| p.z).min().unwrap() - 1,
+    };
+    let max = Point {
+        x: cubes.iter().map(|p| p.x).max().unwrap() + 1,
+        y: cubes.iter().map(|p| p.y).max().unwrap() + 1,
+        z: cubes.iter().map(|p| p.z).max().unwrap() + 1,
+    };
+
+    let mut queue = VecDeque::new();
+    queue.push_back(min);
+    while let Some(point) = queue.pop_front() {
+        if point.x < min.x
+            || point.y < min.y
+            || point.z < min.z
+            || point.x > max.x
+            || point.y > max.y
+            || point.z > max.z
+            || cubes.contains(&point)
+            || !outside.insert(point)
+        {
+            continue;
+        }
+        for dimension in DIMENSIONS.iter() {
+            for direction in DIRECTIONS.iter() {
+                queue.push_back(point.shift(*dimension, *direction));
+            }
+        }
+    }
+
+    outside
+}
+
+fn exterior_and_interior_sides(
+    cubes: &[Point],
+    open_sides: &HashSet<CubeSide>,
+) -> (HashSet<CubeSide>, HashSet<CubeSide>) {
+    let outside = outside_air(&cubes.iter().cloned().collect());
+    open_sides
+        .iter()
+        .partition(|side| outside.contains(&side.outer_cube()))
+}
+
+#[derive(Debug)]
+struct AirPocket {
+    cells: HashSet<Point>,
+    surface_area: usize,
+}
+
+impl AirPocket {
+    fn volume(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+fn air_pockets(cubes: &[Point], open_sides: &HashSet<CubeSide>) -> Vec<AirPocket> {
+    let lava: HashSet<Point> = cubes.iter().cloned().collect();
+    let (_, interior) = exterior_and_interior_sides(cubes, open_sides);
+    let mut seeds = interior.iter().map(|s| s.outer_cube()).collect::<Vec<_>>();
+    seeds.sort();
+
+    let mut visited: HashSet<Point> = HashSet::new();
+    let mut pockets = vec![];
+    for seed in seeds {
+        if visited.contains(&seed) {
+            continue;
+        }
+        let mut cells = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(seed);
+        while let Some(cell) = queue.pop_front() {
+            if lava.contains(&cell) || !cells.insert(cell) {
+                continue;
+            }
+            for dimension in DIMENSIONS.iter() {
+                for direction in DIRECTIONS.iter() {
+                    queue.push_back(cell.shift(*dimension, *direction));
+                }
+            }
+        }
+        visited.extend(cells.iter());
+        let surface_area = interior
+            .iter()
+            .filter(|s| cells.contains(&s.outer_cube()))
+            .count();
+        pockets.push(AirPocket {
+            cells,
+            surface_area,
+        });
+    }
+
+    pockets
+}
+
+#[derive(Debug)]
+struct DropletReport {
+    volume: usize,
+    total_surface: usize,
+    exterior_surface: usize,
+    pockets: Vec<AirPocket>,
+}
+
+fn droplet_report(cubes: &[Point], open_sides: &HashSet<CubeSide>) -> DropletReport {
+    let (exterior, _) = exterior_and_interior_sides(cubes, open_sides);
+    DropletReport {
+        volume: cubes.iter().collect::<HashSet<_>>().len(),
+        total_surface: open_sides.len(),
+        exterior_surface: exterior.len(),
+        pockets: air_pockets(cubes, open_sides),
+    }
+}
+
+fn print_droplet_report(report: &DropletReport) {
+    println!("Droplet volume: {}", report.volume);
+    println!("Total surface: {}", report.total_surface);
+    println!("Exterior surface: {}", report.exterior_surface);
+    println!("Air pockets: {}", report.pockets.len());
+    for (index, pocket) in report.pockets.iter().enumerate() {
+        let mut cells = pocket.cells.iter().collect::<Vec<_>>();
+        cells.sort();
+        println!(
+            "  #{}: volume {}, surface {}, starting at {},{},{}",
+            index + 1,
+            pocket.volume(),
+            pocket.surface_area,
+            cells[0].x,
+            cells[0].y,
+            cells[0].z
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum MeshFormat {
+    Obj,
+    Stl,
+}
+
+struct MeshGroup {
+    name: String,
+    sides: Vec<CubeSide>,
+}
+
+impl MeshGroup {
+    fn new(name: &str, sides: &HashSet<CubeSide>) -> MeshGroup {
+        let mut sides = sides.iter().cloned().collect::<Vec<_>>();
+        sides.sort();
+        MeshGroup {
+            name: name.to_string(),
+            sides,
+        }
+    }
+}
+
+fn mesh_groups(
+    cubes: &[Point],
+    open_sides: &HashSet<CubeSide>,
+    with_pockets: bool,
+) -> Vec<MeshGroup> {
+    let (exterior, interior) = exterior_and_interior_sides(cubes, open_sides);
+    let mut groups = vec![MeshGroup::new("exterior", &exterior)];
+    if with_pockets && !interior.is_empty() {
+        groups.push(MeshGroup::new("interior", &interior));
+    }
+    groups
+}
+
+fn export_mesh(
+    path: &str,
+    format: MeshFormat,
+    cubes: &[Point],
+    open_sides: &HashSet<CubeSide>,
+    with_pockets: bool,
+) -> io::Result<()> {
+    let groups = mesh_groups(cubes, open_sides, with_pockets);
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        MeshFormat::Obj => write_obj(&mut writer, &groups),
+        MeshFormat::Stl => write_stl(&mut writer, &groups),
+    }?;
+    writer.flush()
+}
+
+fn write_obj(writer: &mut impl Write, groups: &[MeshGroup]) -> io::Result<()> {
+    let mut vertex_indices: HashMap<Point, usize> = HashMap::new();
+    for corner in groups
+        .iter()
+        .flat_map(|g| g.sides.iter())
+        .flat_map(|s| s.corners())
+    {
+        if !vertex_indices.contains_key(&corner) {
+            vertex_indices.insert(corner, vertex_indices.len() + 1);
+            writeln!(writer, "v {} {} {}", corner.x, corner.y, corner.z)?;
+        }
+    }
+
+    let normals = DIMENSIONS
+        .iter()
+        .flat_map(|dimension| DIRECTIONS.iter().map(|direction| (*dimension, *direction)))
+        .collect::<Vec<_>>();
+    for (dimension, direction) in normals.iter() {
+        let normal = Point { x: 0, y: 0, z: 0 }.shift(*dimension, *direction);
+        writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+    }
+
+    for group in groups {
+        writeln!(writer, "g {}", group.name)?;
+        for side in group.sides.iter() {
+            let normal_index = normals
+                .iter()
+                .position(|n| *n == (side.dimension, side.direction))
+                .unwrap()
+                + 1;
+            let face = side
+                .corners()
+                .iter()
+                .map(|c| format!("{}//{}", vertex_indices[c], normal_index))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(writer, "f {}", face)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_stl(writer: &mut impl Write, groups: &[MeshGroup]) -> io::Result<()> {
+    for group in groups {
+        writeln!(writer, "solid {}", group.name)?;
+        for side in group.sides.iter() {
+            let normal = side.normal();
+            let [a, b, c, d] = side.corners();
+            for triangle in [[a, b, c], [a, c, d]] {
+                writeln!(
+                    writer,
+                    "  facet normal {} {} {}",
+                    normal.x, normal.y, normal.z
+                )?;
+                writeln!(writer, "    outer loop")?;
+                for vertex in triangle {
+                    writeln!(
+                        writer,
+                        "      vertex {} {} {}",
+                        vertex.x, vertex.y, vertex.z
+                    )?;
+                }
+                writeln!(writer, "    endloop")?;
+                writeln!(writer, "  endfacet")?;
             }
         }
+        writeln!(writer, "endsolid {}", group.name)?;
     }
 
-    connected_components_sizes
+    Ok(())
 }
 
 #[cfg(test)]
@@ -198,13 +523,13 @@ mod tests {
     use std::collections::{hash_map::RandomState, HashSet};
 
     use crate::{
-        calculate_open_sides, connected_components_sizes, parse_point, CubeSide, Dimension,
-        Direction, Point,
+        air_pockets, calculate_open_sides, connected_components_sizes, droplet_report,
+        exterior_and_interior_sides, mesh_groups, parse_point, write_obj, write_stl, CubeSide,
+        Dimension, Direction, Point,
     };
 
-    #[test]
-    fn example_from_description() {
-        let cubes = "2,2,2
+    fn example_cubes() -> Vec<Point> {
+        "2,2,2
         1,2,2
         3,2,2
         2,1,2
@@ -220,12 +545,98 @@ mod tests {
             .lines()
             .map(|l| parse_point(l.trim()))
             .collect::<Result<Vec<_>, _>>()
-            .unwrap();
+            .unwrap()
+    }
+
+    #[test]
+    fn example_from_description() {
+        let cubes = example_cubes();
 
         let open_sides = calculate_open_sides(&cubes);
         assert_eq!(vec![58, 6], connected_components_sizes(&open_sides));
     }
 
+    #[test]
+    fn test_exterior_and_interior_sides() {
+        let cubes = example_cubes();
+        let open_sides = calculate_open_sides(&cubes);
+
+        let (exterior, interior) = exterior_and_interior_sides(&cubes, &open_sides);
+        assert_eq!(58, exterior.len());
+        assert_eq!(6, interior.len());
+        assert!(interior
+            .iter()
+            .all(|s| s.outer_cube() == Point { x: 2, y: 2, z: 5 }));
+    }
+
+    #[test]
+    fn test_air_pockets() {
+        let cubes = example_cubes();
+        let open_sides = calculate_open_sides(&cubes);
+
+        let pockets = air_pockets(&cubes, &open_sides);
+        assert_eq!(1, pockets.len());
+        assert_eq!(1, pockets[0].volume());
+        assert_eq!(6, pockets[0].surface_area);
+        assert!(pockets[0].cells.contains(&Point { x: 2, y: 2, z: 5 }));
+
+        let mut cubes = vec![];
+        for x in 0..5 {
+            for y in 0..4 {
+                for z in 0..4 {
+                    if !(x == 1 || x == 3) || !(y == 1 || y == 2) || !(z == 1 || z == 2) {
+                        cubes.push(Point { x, y, z });
+                    }
+                }
+            }
+        }
+        let open_sides = calculate_open_sides(&cubes);
+        let report = droplet_report(&cubes, &open_sides);
+        assert_eq!(72, report.volume);
+        assert_eq!(2, report.pockets.len());
+        assert_eq!(
+            vec![(4, 16), (4, 16)],
+            report
+                .pockets
+                .iter()
+                .map(|p| (p.volume(), p.surface_area))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(112, report.exterior_surface);
+        assert_eq!(112 + 32, report.total_surface);
+    }
+
+    #[test]
+    fn test_mesh_export() {
+        let cubes = [Point { x: 0, y: 0, z: 0 }];
+        let groups = mesh_groups(&cubes, &calculate_open_sides(&cubes), true);
+        assert_eq!(1, groups.len());
+
+        let mut obj = vec![];
+        write_obj(&mut obj, &groups).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(8, obj.lines().filter(|l| l.starts_with("v ")).count());
+        assert_eq!(6, obj.lines().filter(|l| l.starts_with("vn ")).count());
+        assert_eq!(6, obj.lines().filter(|l| l.starts_with("f ")).count());
+        assert!(obj.contains("g exterior"));
+
+        let mut stl = vec![];
+        write_stl(&mut stl, &groups).unwrap();
+        let stl = String::from_utf8(stl).unwrap();
+        assert_eq!(
+            12,
+            stl.lines().filter(|l| l.contains("facet normal")).count()
+        );
+        assert!(stl.starts_with("solid exterior"));
+
+        let cubes = example_cubes();
+        let groups = mesh_groups(&cubes, &calculate_open_sides(&cubes), true);
+        assert_eq!(
+            vec![58, 6],
+            groups.iter().map(|g| g.sides.len()).collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn test_adjacent_sides() {
         let open_sides = calculate_open_sides(&[
@@ -1,4 +1,14 @@
-use std::ops;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    env, ops,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
 
 #[macro_use]
 extern crate lazy_static;
@@ -18,24 +28,157 @@ fn main() {
         }
     };
 
+    let args = env::args().collect::<Vec<_>>();
+    let workers = match args
+        .iter()
+        .position(|a| a == "--workers")
+        .and_then(|i| args.get(i + 1))
+    {
+        Some(workers) => match workers.parse() {
+            Ok(workers) if workers > 0 => workers,
+            _ => {
+                println!("Invalid number of workers: '{}'", workers);
+                return;
+            }
+        },
+        None => default_workers(),
+    };
+
+    if let Some(id) = args
+        .iter()
+        .position(|a| a == "--explain")
+        .and_then(|i| args.get(i + 1))
+    {
+        let minutes = match args
+            .iter()
+            .position(|a| a == "--minutes")
+            .and_then(|i| args.get(i + 1))
+            .map(|m| m.parse())
+        {
+            Some(Ok(minutes)) => minutes,
+            Some(Err(_)) => {
+                println!("Invalid number of minutes");
+                return;
+            }
+            None => 24,
+        };
+        match blueprints.iter().find(|b| id.parse() == Ok(b.id)) {
+            Some(blueprint) => {
+                let (max_geodes, schedule) = blueprint.max_geodes_with_schedule(minutes);
+                println!(
+                    "Blueprint {}: {} {}",
+                    blueprint.id, max_geodes, blueprint.resources[blueprint.target]
+                );
+                print!("{}", blueprint.explain(minutes, &schedule));
+            }
+            None => println!("Unknown blueprint: '{}'", id),
+        }
+        return;
+    }
+
+    if args.iter().any(|a| a == "--bench") {
+        benchmark(&blueprints, workers);
+        return;
+    }
+
     println!(
         "Part 1: {}",
-        blueprints
+        evaluate_in_parallel(&blueprints, workers, |b| b.quality_level(24))
             .iter()
-            .map(|b| b.quality_level(24))
             .sum::<isize>()
     );
     println!(
         "Part 2: {}",
-        blueprints
-            .iter()
-            .take(3)
-            .map(|b| b.max_geodes(32))
-            .product::<isize>()
+        evaluate_in_parallel(&blueprints[..blueprints.len().min(3)], workers, |b| {
+            b.max_geodes(32)
+        })
+        .iter()
+        .product::<isize>()
     );
 }
 
+fn default_workers() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+fn evaluate_in_parallel<T, F>(blueprints: &[Blueprint], workers: usize, evaluate: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&Blueprint) -> T + Sync,
+{
+    let next_index = AtomicUsize::new(0);
+    let results = Mutex::new((0..blueprints.len()).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, blueprints.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                if index >= blueprints.len() {
+                    break;
+                }
+                let result = evaluate(&blueprints[index]);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+fn benchmark(blueprints: &[Blueprint], workers: usize) {
+    let mut single_threaded = vec![];
+    for workers in [1, workers] {
+        let start = Instant::now();
+        let part1 = evaluate_in_parallel(blueprints, workers, |b| b.quality_level(24));
+        let part1_elapsed = start.elapsed();
+        let start = Instant::now();
+        let part2 = evaluate_in_parallel(&blueprints[..blueprints.len().min(3)], workers, |b| {
+            b.max_geodes(32)
+        });
+        let part2_elapsed = start.elapsed();
+
+        println!(
+            "{} worker(s): part 1 {:?}, part 2 {:?}",
+            workers, part1_elapsed, part2_elapsed
+        );
+        if single_threaded.is_empty() {
+            single_threaded = vec![part1, part2];
+        } else if single_threaded != vec![part1, part2] {
+            println!("Results differ from the single-threaded run");
+        }
+    }
+
+    for (name, config) in [
+        ("basic", SearchConfig::basic()),
+        ("full", SearchConfig::default()),
+    ] {
+        let start = Instant::now();
+        let results = evaluate_in_parallel(blueprints, workers, |b| b.search(24, &config));
+        println!(
+            "{} pruning: {} states explored in {:?}",
+            name,
+            results.iter().map(|r| r.explored_states).sum::<usize>(),
+            start.elapsed()
+        );
+    }
+}
+
 fn parse_blueprint(str: &str) -> Result<Blueprint, String> {
+    if str.starts_with("Factory ") {
+        parse_factory(str)
+    } else {
+        parse_puzzle_blueprint(str)
+    }
+}
+
+fn parse_puzzle_blueprint(str: &str) -> Result<Blueprint, String> {
     lazy_static! {
         static ref RE: Regex = Regex::new(r"^Blueprint (\d+): Each ore robot costs (\d+) ore. Each clay robot costs (\d+) ore. Each obsidian robot costs (\d+) ore and (\d+) clay. Each geode robot costs (\d+) ore and (\d+) obsidian.$").unwrap();
     }
@@ -88,93 +231,180 @@ fn parse_blueprint(str: &str) -> Result<Blueprint, String> {
         .ok_or(format!("Couldn't parse blueprint out of '{}'", str))
 }
 
-#[derive(Clone, Debug)]
+fn parse_factory(str: &str) -> Result<Blueprint, String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^Factory (\d+):(.+)$").unwrap();
+        static ref ROLE_RE: Regex = Regex::new(r"^(start|target) (\w+)$").unwrap();
+        static ref RECIPE_RE: Regex = Regex::new(r"^(\w+) <-(.*)$").unwrap();
+        static ref COST_RE: Regex = Regex::new(r"^(\d+) (\w+)$").unwrap();
+    }
+
+    let captures = RE
+        .captures(str)
+        .ok_or(format!("Couldn't parse factory out of '{}'", str))?;
+    let id = captures[1]
+        .parse()
+        .map_err(|_| format!("Invalid factory id in '{}'", str))?;
+
+    let mut resources: Vec<String> = vec![];
+    let mut index_of = |name: &str| match resources.iter().position(|r| r == name) {
+        Some(index) => index,
+        None => {
+            resources.push(name.to_string());
+            resources.len() - 1
+        }
+    };
+    let mut recipes = vec![];
+    let (mut start, mut target) = (None, None);
+    for recipe in captures[2].split(';').map(|r| r.trim()) {
+        if let Some(role_captures) = ROLE_RE.captures(recipe) {
+            let role = if &role_captures[1] == "start" {
+                &mut start
+            } else {
+                &mut target
+            };
+            if role.is_some() {
+                return Err(format!(
+                    "Factory {} names more than one {} robot",
+                    id, &role_captures[1]
+                ));
+            }
+            *role = Some(role_captures[2].to_string());
+            continue;
+        }
+        let recipe_captures = RECIPE_RE
+            .captures(recipe)
+            .ok_or(format!("Couldn't parse recipe out of '{}'", recipe))?;
+        let robot = index_of(&recipe_captures[1]);
+        let mut cost = vec![];
+        for component in recipe_captures[2]
+            .split(',')
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+        {
+            let cost_captures = COST_RE
+                .captures(component)
+                .ok_or(format!("Couldn't parse cost out of '{}'", component))?;
+            let amount: isize = cost_captures[1]
+                .parse()
+                .map_err(|_| format!("Invalid amount in '{}'", component))?;
+            cost.push((index_of(&cost_captures[2]), amount));
+        }
+        recipes.push((robot, cost));
+    }
+
+    if recipes.is_empty() {
+        return Err(format!("No recipes in '{}'", str));
+    }
+    let resource_named = |role: &str, name: Option<String>| -> Result<usize, String> {
+        let name = name.ok_or(format!(
+            "Factory {} doesn't name its {} robot, add '{} <resource>'",
+            id, role, role
+        ))?;
+        resources.iter().position(|r| *r == name).ok_or(format!(
+            "Factory {} names unknown resource '{}' as its {} robot",
+            id, name, role
+        ))
+    };
+    let start = resource_named("start", start)?;
+    let target = resource_named("target", target)?;
+    let resources_count = resources.len();
+    Blueprint::from_recipes(
+        id,
+        resources,
+        recipes
+            .into_iter()
+            .map(|(robot, cost)| {
+                let mut amounts = Amounts::zero(resources_count);
+                for (resource, amount) in cost {
+                    amounts.0[resource] += amount;
+                }
+                Recipe {
+                    robot,
+                    cost: amounts,
+                }
+            })
+            .collect(),
+        start,
+        target,
+    )
+}
+
 struct Resources {
     ore: isize,
     clay: isize,
     obsidian: isize,
 }
 
-impl Resources {
-    fn produced_by(robot: Robot) -> Resources {
-        match robot {
-            Robot::Ore => Resources {
-                ore: 1,
-                clay: 0,
-                obsidian: 0,
-            },
-            Robot::Clay => Resources {
-                ore: 0,
-                clay: 1,
-                obsidian: 0,
-            },
-            Robot::Obsidian => Resources {
-                ore: 0,
-                clay: 0,
-                obsidian: 1,
-            },
-            Robot::Geode => Resources {
-                ore: 0,
-                clay: 0,
-                obsidian: 0,
-            },
-        }
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Amounts(Vec<isize>);
+
+impl Amounts {
+    fn zero(len: usize) -> Amounts {
+        Amounts(vec![0; len])
+    }
+
+    fn unit(len: usize, index: usize) -> Amounts {
+        let mut amounts = Amounts::zero(len);
+        amounts.0[index] = 1;
+        amounts
+    }
+
+    fn covers(&self, other: &Amounts) -> bool {
+        self.0.iter().zip(other.0.iter()).all(|(a, b)| a >= b)
     }
 }
 
-impl ops::Add<&Resources> for &Resources {
-    type Output = Resources;
+impl ops::Add<&Amounts> for &Amounts {
+    type Output = Amounts;
 
-    fn add(self, other: &Resources) -> Resources {
-        Resources {
-            ore: self.ore + other.ore,
-            clay: self.clay + other.clay,
-            obsidian: self.obsidian + other.obsidian,
-        }
+    fn add(self, other: &Amounts) -> Amounts {
+        Amounts(
+            self.0
+                .iter()
+                .zip(other.0.iter())
+                .map(|(a, b)| a + b)
+                .collect(),
+        )
     }
 }
 
-impl ops::Sub<&Resources> for &Resources {
-    type Output = Resources;
+impl ops::Sub<&Amounts> for &Amounts {
+    type Output = Amounts;
 
-    fn sub(self, other: &Resources) -> Resources {
-        Resources {
-            ore: self.ore - other.ore,
-            clay: self.clay - other.clay,
-            obsidian: self.obsidian - other.obsidian,
-        }
+    fn sub(self, other: &Amounts) -> Amounts {
+        Amounts(
+            self.0
+                .iter()
+                .zip(other.0.iter())
+                .map(|(a, b)| a - b)
+                .collect(),
+        )
     }
 }
 
-impl ops::Mul<isize> for &Resources {
-    type Output = Resources;
+impl ops::Mul<isize> for &Amounts {
+    type Output = Amounts;
 
-    fn mul(self, multiplicand: isize) -> Resources {
-        Resources {
-            ore: self.ore * multiplicand,
-            clay: self.clay * multiplicand,
-            obsidian: self.obsidian * multiplicand,
-        }
+    fn mul(self, multiplicand: isize) -> Amounts {
+        Amounts(self.0.iter().map(|a| a * multiplicand).collect())
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-enum Robot {
-    Ore,
-    Clay,
-    Obsidian,
-    Geode,
+#[derive(Clone, Debug)]
+struct Recipe {
+    robot: usize,
+    cost: Amounts,
 }
 
 struct Blueprint {
     id: isize,
-    ore_cost: Resources,
-    clay_cost: Resources,
-    obsidian_cost: Resources,
-    geode_cost: Resources,
-    max_ore_needed: isize,
-    max_clay_needed: isize,
-    max_obsidian_needed: isize,
+    resources: Vec<String>,
+    recipes: Vec<Recipe>,
+    start: usize,
+    target: usize,
+    max_needed: Vec<isize>,
+    keep_at_least_minutes_left_after: Vec<Option<isize>>,
 }
 
 impl Blueprint {
@@ -185,21 +415,85 @@ impl Blueprint {
         obsidian_cost: Resources,
         geode_cost: Resources,
     ) -> Blueprint {
-        let all_costs = [&ore_cost, &clay_cost, &obsidian_cost, &geode_cost];
-        let max_ore_needed = all_costs.iter().map(|c| c.ore).max().unwrap();
-        let max_clay_needed = all_costs.iter().map(|c| c.clay).max().unwrap();
-        let max_obsidian_needed = all_costs.iter().map(|c| c.obsidian).max().unwrap();
+        let recipes = [ore_cost, clay_cost, obsidian_cost, geode_cost]
+            .iter()
+            .enumerate()
+            .map(|(robot, cost)| Recipe {
+                robot,
+                cost: Amounts(vec![cost.ore, cost.clay, cost.obsidian, 0]),
+            })
+            .collect();
 
-        Blueprint {
+        Blueprint::from_recipes(
             id,
-            ore_cost,
-            clay_cost,
-            obsidian_cost,
-            geode_cost,
-            max_ore_needed,
-            max_clay_needed,
-            max_obsidian_needed,
+            ["ore", "clay", "obsidian", "geode"]
+                .iter()
+                .map(|r| r.to_string())
+                .collect(),
+            recipes,
+            0,
+            3,
+        )
+        .unwrap()
+    }
+
+    fn from_recipes(
+        id: isize,
+        resources: Vec<String>,
+        recipes: Vec<Recipe>,
+        start: usize,
+        target: usize,
+    ) -> Result<Blueprint, String> {
+        if recipes.is_empty() {
+            return Err(format!("Blueprint {} has no recipes", id));
+        }
+        for (index, recipe) in recipes.iter().enumerate() {
+            if recipes[..index].iter().any(|r| r.robot == recipe.robot) {
+                return Err(format!(
+                    "Blueprint {} has more than one recipe for {} robots",
+                    id, resources[recipe.robot]
+                ));
+            }
+        }
+        if !recipes.iter().any(|r| r.robot == target) {
+            return Err(format!(
+                "Blueprint {} has no recipe for {} robots",
+                id, resources[target]
+            ));
         }
+
+        let max_needed = (0..resources.len())
+            .map(|resource| recipes.iter().map(|r| r.cost.0[resource]).max().unwrap())
+            .collect();
+
+        let mut distance_to_target = vec![None; resources.len()];
+        distance_to_target[target] = Some(0);
+        for _ in 0..resources.len() {
+            for recipe in recipes.iter() {
+                let distance = match distance_to_target[recipe.robot] {
+                    Some(distance) => distance + 1,
+                    None => continue,
+                };
+                for (resource, amount) in recipe.cost.0.iter().enumerate() {
+                    if *amount > 0 && distance_to_target[resource].is_none_or(|d| d > distance) {
+                        distance_to_target[resource] = Some(distance);
+                    }
+                }
+            }
+        }
+
+        Ok(Blueprint {
+            id,
+            resources,
+            recipes,
+            start,
+            target,
+            max_needed,
+            keep_at_least_minutes_left_after: distance_to_target
+                .iter()
+                .map(|d| d.map(|d| d + 1))
+                .collect(),
+        })
     }
 
     fn quality_level(&self, minutes: isize) -> isize {
@@ -207,39 +501,224 @@ impl Blueprint {
     }
 
     fn max_geodes(&self, minutes: isize) -> isize {
+        self.search(minutes, &SearchConfig::default()).max_geodes
+    }
+
+    fn max_geodes_with_schedule(&self, minutes: isize) -> (isize, Vec<BuildStep>) {
+        let result = self.search(
+            minutes,
+            &SearchConfig {
+                record_schedule: true,
+                ..SearchConfig::default()
+            },
+        );
+        (result.max_geodes, result.schedule.unwrap_or_default())
+    }
+
+    fn search(&self, minutes: isize, config: &SearchConfig) -> SearchResult {
         let mut stack = Vec::new();
-        let mut max = 0;
+        let mut max = None;
+        let mut best_schedule = None;
+        let mut explored_states = 0;
+        let mut transpositions: HashMap<StateKey, isize> = HashMap::new();
 
-        stack.push(State::initial(minutes));
+        stack.push(State::initial(minutes, self.resources.len(), self.start));
 
         while let Some(state) = stack.pop() {
+            explored_states += 1;
+            let geodes = state.resources.0[self.target];
             if state.minutes_left == 0 {
-                if state.geodes > max {
-                    max = state.geodes
+                if max.is_none_or(|max| geodes > max) {
+                    max = Some(geodes);
+                    best_schedule = state.schedule.clone();
                 }
-            } else {
-                if state.theoretical_max_geodes() > max {
-                    for next_state in self.next_states(&state) {
-                        stack.push(next_state);
+                continue;
+            }
+            if let Some(max) = max {
+                if state.theoretical_max(self.target) <= max
+                    || config.greedy_bound && self.greedy_max_geodes(&state) <= max
+                {
+                    continue;
+                }
+            }
+            if config.transpositions {
+                match transpositions.entry(self.state_key(&state)) {
+                    Entry::Occupied(mut entry) => {
+                        if *entry.get() >= geodes {
+                            continue;
+                        }
+                        entry.insert(geodes);
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(geodes);
                     }
                 }
             }
+            for next_state in self.next_states(&state, config) {
+                stack.push(next_state);
+            }
         }
 
-        max
+        SearchResult {
+            max_geodes: max.unwrap_or(0),
+            explored_states,
+            schedule: best_schedule.map(|s| s.steps(minutes)),
+        }
+    }
+
+    fn recipe(&self, robot: usize) -> &Recipe {
+        self.recipes.iter().find(|r| r.robot == robot).unwrap()
+    }
+
+    fn robot_name(&self, robot: usize) -> String {
+        match self.resources[robot].as_str() {
+            "geode" => "geode-cracking robot".to_string(),
+            name => format!("{}-collecting robot", name),
+        }
+    }
+
+    fn explain(&self, minutes: isize, schedule: &[BuildStep]) -> String {
+        let mut lines = vec![];
+        let mut state = State::initial(minutes, self.resources.len(), self.start);
+
+        for minute in 1..=minutes {
+            lines.push(format!("== Minute {} ==", minute));
+            let built = schedule
+                .iter()
+                .find(|s| s.minute == minute)
+                .map(|s| s.robot);
+            if let Some(robot) = built {
+                let cost = &self.recipe(robot).cost;
+                let spent = cost
+                    .0
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, amount)| **amount > 0)
+                    .map(|(resource, amount)| format!("{} {}", amount, self.resources[resource]))
+                    .collect::<Vec<_>>()
+                    .join(" and ");
+                let name = self.robot_name(robot);
+                let article = if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                    "an"
+                } else {
+                    "a"
+                };
+                lines.push(format!(
+                    "Spend {} to start building {} {}.",
+                    spent, article, name
+                ));
+                state.resources = &state.resources - cost;
+            }
+            for robot in self.recipes.iter().map(|r| r.robot) {
+                let count = state.robots.0[robot];
+                if count == 0 {
+                    continue;
+                }
+                state.resources.0[robot] += count;
+                let plural = |n: isize| if n == 1 { "" } else { "s" };
+                let verb_suffix = if count == 1 { "s" } else { "" };
+                let line = match self.resources[robot].as_str() {
+                    "geode" => format!(
+                        "{} geode-cracking robot{} crack{} {} geode{}; you now have {} open geode{}.",
+                        count,
+                        plural(count),
+                        verb_suffix,
+                        count,
+                        plural(count),
+                        state.resources.0[robot],
+                        plural(state.resources.0[robot]),
+                    ),
+                    name => format!(
+                        "{} {}{} collect{} {} {}; you now have {} {}.",
+                        count,
+                        self.robot_name(robot),
+                        plural(count),
+                        verb_suffix,
+                        count,
+                        name,
+                        state.resources.0[robot],
+                        name,
+                    ),
+                };
+                lines.push(line);
+            }
+            if let Some(robot) = built {
+                state.robots.0[robot] += 1;
+                lines.push(format!(
+                    "The new {} is ready; you now have {} of them.",
+                    self.robot_name(robot),
+                    state.robots.0[robot]
+                ));
+            }
+            lines.push(String::new());
+        }
+
+        lines.join("\n")
     }
 
-    fn next_states(&self, state: &State) -> Vec<State> {
-        lazy_static! {
-            static ref ALL_ROBOTS: [Robot; 4] =
-                [Robot::Ore, Robot::Clay, Robot::Obsidian, Robot::Geode];
+    fn greedy_max_geodes(&self, state: &State) -> isize {
+        let mut robots = state.robots.clone();
+        let mut pools = vec![state.resources.clone(); self.recipes.len()];
+        let mut geodes = state.resources.0[self.target];
+
+        for _ in 0..state.minutes_left {
+            let built = self
+                .recipes
+                .iter()
+                .enumerate()
+                .filter(|(index, recipe)| pools[*index].covers(&recipe.cost))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            geodes += robots.0[self.target];
+            for pool in pools.iter_mut() {
+                *pool = &*pool + &robots;
+            }
+            for index in built {
+                pools[index] = &pools[index] - &self.recipes[index].cost;
+                robots.0[self.recipes[index].robot] += 1;
+            }
         }
+
+        geodes
+    }
+
+    fn state_key(&self, state: &State) -> StateKey {
+        (
+            state.minutes_left,
+            state.robots.0.clone(),
+            state
+                .resources
+                .0
+                .iter()
+                .enumerate()
+                .map(|(resource, amount)| {
+                    if resource == self.target {
+                        0
+                    } else {
+                        (*amount).min(self.max_needed[resource] * state.minutes_left)
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    fn next_states(&self, state: &State, config: &SearchConfig) -> Vec<State> {
         let mut states = vec![];
 
+        // Never wait when a target robot can be built right away. This is a heuristic rather
+        // than an exact rule: saving up for another robot can occasionally pay off, so
+        // SearchConfig::basic() leaves it off and the tests check it against the full search.
+        if config.geode_dominance && state.resources.covers(&self.recipe(self.target).cost) {
+            if let Some(next_state) = self.try_build_if_needed(state, self.target, config) {
+                states.push(next_state);
+                return states;
+            }
+        }
+
         states.extend(
-            ALL_ROBOTS
+            self.recipes
                 .iter()
-                .filter_map(|robot| self.try_build_if_needed(state, *robot)),
+                .filter_map(|recipe| self.try_build_if_needed(state, recipe.robot, config)),
         );
         if states.is_empty() {
             states.push(state.wait_until_end());
@@ -248,59 +727,120 @@ impl Blueprint {
         states
     }
 
-    fn try_build_if_needed(&self, state: &State, robot: Robot) -> Option<State> {
-        let robot_needed = match robot {
-            Robot::Ore => self.max_ore_needed > state.robots.ore,
-            Robot::Clay => self.max_clay_needed > state.robots.clay,
-            Robot::Obsidian => self.max_obsidian_needed > state.robots.obsidian,
-            Robot::Geode => true,
-        };
+    fn try_build_if_needed(
+        &self,
+        state: &State,
+        robot: usize,
+        config: &SearchConfig,
+    ) -> Option<State> {
+        let robot_needed = robot == self.target || self.max_needed[robot] > state.robots.0[robot];
         if !robot_needed {
             return None;
         }
-        let (cost, keep_at_least_minutes_left_after) = match robot {
-            Robot::Ore => (&self.ore_cost, 2),
-            Robot::Clay => (&self.clay_cost, 3),
-            Robot::Obsidian => (&self.obsidian_cost, 2),
-            Robot::Geode => (&self.geode_cost, 1),
-        };
-        state.try_build(robot, cost, keep_at_least_minutes_left_after)
+        let keep_at_least_minutes_left_after = self.keep_at_least_minutes_left_after[robot]?;
+        state.try_build(
+            robot,
+            &self.recipe(robot).cost,
+            keep_at_least_minutes_left_after,
+            config.record_schedule,
+        )
+    }
+}
+
+struct SearchConfig {
+    greedy_bound: bool,
+    transpositions: bool,
+    geode_dominance: bool,
+    record_schedule: bool,
+}
+
+impl SearchConfig {
+    fn basic() -> SearchConfig {
+        SearchConfig {
+            greedy_bound: false,
+            transpositions: false,
+            geode_dominance: false,
+            record_schedule: false,
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> SearchConfig {
+        SearchConfig {
+            greedy_bound: true,
+            transpositions: true,
+            geode_dominance: true,
+            record_schedule: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct SearchResult {
+    max_geodes: isize,
+    explored_states: usize,
+    schedule: Option<Vec<BuildStep>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BuildStep {
+    minute: isize,
+    robot: usize,
+    resources_after: Amounts,
+}
+
+#[derive(Debug)]
+struct Schedule {
+    robot: usize,
+    minutes_left: isize,
+    resources_after: Amounts,
+    previous: Option<Rc<Schedule>>,
+}
+
+impl Schedule {
+    fn steps(&self, minutes: isize) -> Vec<BuildStep> {
+        let mut steps = vec![];
+        let mut current = Some(self);
+        while let Some(schedule) = current {
+            steps.push(BuildStep {
+                minute: minutes - schedule.minutes_left,
+                robot: schedule.robot,
+                resources_after: schedule.resources_after.clone(),
+            });
+            current = schedule.previous.as_deref();
+        }
+        steps.reverse();
+        steps
     }
 }
 
+type StateKey = (isize, Vec<isize>, Vec<isize>);
+
 #[derive(Debug)]
 struct State {
     minutes_left: isize,
-    robots: Resources,
-    resources: Resources,
-    geode_robots: isize,
-    geodes: isize,
+    robots: Amounts,
+    resources: Amounts,
+    schedule: Option<Rc<Schedule>>,
 }
 
 impl State {
-    fn initial(minutes: isize) -> State {
+    fn initial(minutes: isize, resources_count: usize, starting_robot: usize) -> State {
         State {
             minutes_left: minutes,
-            robots: Resources {
-                ore: 1,
-                clay: 0,
-                obsidian: 0,
-            },
-            resources: Resources {
-                ore: 0,
-                clay: 0,
-                obsidian: 0,
-            },
-            geode_robots: 0,
-            geodes: 0,
+            robots: Amounts::unit(resources_count, starting_robot),
+            resources: Amounts::zero(resources_count),
+            schedule: None,
         }
     }
 
     fn try_build(
         &self,
-        robot: Robot,
-        robot_cost: &Resources,
+        robot: usize,
+        robot_cost: &Amounts,
         keep_at_least_minutes_left_after: isize,
+        record_schedule: bool,
     ) -> Option<State> {
         let minutes_needed = match self.minutes_needed_to_build(robot_cost) {
             Some(minutes_needed) => minutes_needed,
@@ -311,18 +851,24 @@ impl State {
             return None;
         }
 
-        let built_resources_robots = Resources::produced_by(robot);
-        let built_geode_robots = match robot {
-            Robot::Geode => 1,
-            _ => 0,
+        let minutes_left = self.minutes_left - minutes_needed;
+        let resources = &(&self.resources + &(&self.robots * minutes_needed)) - robot_cost;
+        let schedule = if record_schedule {
+            Some(Rc::new(Schedule {
+                robot,
+                minutes_left,
+                resources_after: resources.clone(),
+                previous: self.schedule.clone(),
+            }))
+        } else {
+            None
         };
 
         Some(State {
-            minutes_left: self.minutes_left - minutes_needed,
-            robots: &self.robots + &built_resources_robots,
-            resources: &(&self.resources + &(&self.robots * minutes_needed)) - robot_cost,
-            geode_robots: self.geode_robots + built_geode_robots,
-            geodes: self.geodes + self.geode_robots * minutes_needed,
+            minutes_left,
+            robots: &self.robots + &Amounts::unit(self.robots.0.len(), robot),
+            resources,
+            schedule,
         })
     }
 
@@ -331,101 +877,203 @@ impl State {
             minutes_left: 0,
             robots: self.robots.clone(),
             resources: &self.resources + &(&self.robots * self.minutes_left),
-            geode_robots: self.geode_robots,
-            geodes: self.geodes + self.geode_robots * self.minutes_left,
+            schedule: self.schedule.clone(),
         }
     }
 
-    fn theoretical_max_geodes(&self) -> isize {
-        let mut max_geodes = self.geodes;
+    fn theoretical_max(&self, resource: usize) -> isize {
+        let mut max = self.resources.0[resource];
         for minute in 0..self.minutes_left {
-            max_geodes += self.geode_robots + minute;
+            max += self.robots.0[resource] + minute;
         }
-        max_geodes
+        max
     }
 
-    fn minutes_needed_to_build(&self, robot_cost: &Resources) -> Option<isize> {
-        if robot_cost.ore != 0 && self.robots.ore == 0
-            || robot_cost.clay != 0 && self.robots.clay == 0
-            || robot_cost.obsidian != 0 && self.robots.obsidian == 0
+    fn minutes_needed_to_build(&self, robot_cost: &Amounts) -> Option<isize> {
+        let mut minutes_to_collect_resources = 0;
+        for ((cost, available), robots) in robot_cost
+            .0
+            .iter()
+            .zip(self.resources.0.iter())
+            .zip(self.robots.0.iter())
         {
-            return None;
+            let resources_needed = cost - available;
+            if resources_needed <= 0 {
+                continue;
+            }
+            if *robots == 0 {
+                return None;
+            }
+            minutes_to_collect_resources =
+                minutes_to_collect_resources.max((resources_needed + robots - 1) / robots);
         }
 
-        let minutes_to_collect_resources = [
-            (robot_cost.ore - self.resources.ore, self.robots.ore),
-            (robot_cost.clay - self.resources.clay, self.robots.clay),
-            (
-                robot_cost.obsidian - self.resources.obsidian,
-                self.robots.obsidian,
-            ),
-        ]
-        .iter()
-        .filter(|(resources_needed, _)| *resources_needed > 0)
-        .map(|(resources_needed, robots)| (resources_needed + robots - 1) / robots)
-        .max()
-        .unwrap_or(0);
-
         Some(minutes_to_collect_resources + 1)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Blueprint, Resources};
+    use crate::{evaluate_in_parallel, parse_blueprint, Blueprint, SearchConfig};
+
+    fn example_blueprints() -> Vec<Blueprint> {
+        [
+            "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.",
+            "Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.",
+        ]
+        .iter()
+        .map(|b| parse_blueprint(b).unwrap())
+        .collect()
+    }
 
     #[test]
     fn examples_from_description() {
-        let blueprint = Blueprint::new(
-            1,
-            Resources {
-                ore: 4,
-                clay: 0,
-                obsidian: 0,
-            },
-            Resources {
-                ore: 2,
-                clay: 0,
-                obsidian: 0,
-            },
-            Resources {
-                ore: 3,
-                clay: 14,
-                obsidian: 0,
-            },
-            Resources {
-                ore: 2,
-                clay: 0,
-                obsidian: 7,
-            },
+        let blueprints = example_blueprints();
+
+        assert_eq!(9, blueprints[0].quality_level(24));
+        assert_eq!(24, blueprints[1].quality_level(24));
+    }
+
+    #[test]
+    fn test_evaluate_in_parallel() {
+        let blueprints = example_blueprints();
+
+        for workers in 1..=3 {
+            assert_eq!(
+                vec![9, 24],
+                evaluate_in_parallel(&blueprints, workers, |b| b.quality_level(24))
+            );
+        }
+        assert_eq!(
+            Vec::<isize>::new(),
+            evaluate_in_parallel(&[], 2, |b| b.max_geodes(24))
         );
+    }
+
+    #[test]
+    fn test_pruning_keeps_results() {
+        let blueprint = &example_blueprints()[0];
 
-        assert_eq!(9, blueprint.quality_level(24));
+        let basic = blueprint.search(24, &SearchConfig::basic());
+        let full = blueprint.search(24, &SearchConfig::default());
+        assert_eq!(9, basic.max_geodes);
+        assert_eq!(9, full.max_geodes);
+        assert!(full.explored_states < basic.explored_states);
 
-        let blueprint = Blueprint::new(
+        assert_eq!(56, blueprint.max_geodes(32));
+    }
+
+    #[test]
+    fn test_geode_dominance_keeps_results() {
+        for (blueprint, expected) in example_blueprints().iter().zip([9, 12]) {
+            let with_dominance = blueprint.search(24, &SearchConfig::default());
+            let without_dominance = blueprint.search(
+                24,
+                &SearchConfig {
+                    geode_dominance: false,
+                    ..SearchConfig::default()
+                },
+            );
+            assert_eq!(expected, with_dominance.max_geodes);
+            assert_eq!(expected, without_dominance.max_geodes);
+            assert!(with_dominance.explored_states < without_dominance.explored_states);
+        }
+    }
+
+    #[test]
+    fn test_schedule_and_explanation() {
+        let blueprint = &example_blueprints()[0];
+
+        let (max_geodes, schedule) = blueprint.max_geodes_with_schedule(24);
+        assert_eq!(9, max_geodes);
+        assert_eq!(
             2,
-            Resources {
-                ore: 2,
-                clay: 0,
-                obsidian: 0,
-            },
-            Resources {
-                ore: 3,
-                clay: 0,
-                obsidian: 0,
-            },
-            Resources {
-                ore: 3,
-                clay: 8,
-                obsidian: 0,
-            },
-            Resources {
-                ore: 3,
-                clay: 0,
-                obsidian: 12,
-            },
+            schedule
+                .iter()
+                .filter(|s| s.robot == blueprint.target)
+                .count()
         );
+        assert!(schedule.windows(2).all(|w| w[0].minute < w[1].minute));
+        assert!(schedule
+            .iter()
+            .all(|s| s.resources_after.0.iter().all(|r| *r >= 0)));
+
+        let explanation = blueprint.explain(24, &schedule);
+        assert!(explanation.starts_with(
+            "== Minute 1 ==\n1 ore-collecting robot collects 1 ore; you now have 1 ore.\n"
+        ));
+        assert!(explanation.contains("to start building a geode-cracking robot."));
+        assert!(explanation.contains("you now have 9 open geodes."));
+        assert_eq!(24, explanation.matches("== Minute").count());
+
+        let (max_geodes, schedule) = blueprint.max_geodes_with_schedule(10);
+        assert_eq!(0, max_geodes);
+        assert!(!schedule.is_empty());
+        let explanation = blueprint.explain(10, &schedule);
+        assert!(explanation.contains("to start building"));
+        assert_eq!(10, explanation.matches("== Minute").count());
+    }
+
+    #[test]
+    fn test_parse_blueprint_dialects() {
+        let puzzle = &example_blueprints()[0];
+        let factory = parse_blueprint(
+            "Factory 1: start ore; target geode; ore <- 4 ore; clay <- 2 ore; obsidian <- 3 ore, 14 clay; geode <- 2 ore, 7 obsidian",
+        )
+        .unwrap();
+
+        assert_eq!(puzzle.resources, factory.resources);
+        assert_eq!(puzzle.target, factory.target);
+        assert_eq!(9, puzzle.quality_level(24));
+        assert_eq!(9, factory.quality_level(24));
+
+        let reordered = parse_blueprint(
+            "Factory 1: geode <- 2 ore, 7 obsidian; obsidian <- 3 ore, 14 clay; clay <- 2 ore; ore <- 4 ore; target geode; start ore",
+        )
+        .unwrap();
+        assert_eq!(9, reordered.quality_level(24));
+
+        assert!(
+            parse_blueprint("Factory 1: start ore; target ore; ore <- 4 ore; ore <- 2 ore")
+                .is_err()
+        );
+        assert!(parse_blueprint("Factory 1: start ore; target ore; ore <- four ore").is_err());
+        assert_eq!(
+            Some("Factory 1 doesn't name its start robot, add 'start <resource>'".to_string()),
+            parse_blueprint("Factory 1: target clay; ore <- 4 ore; clay <- 2 ore").err()
+        );
+        assert_eq!(
+            Some("Factory 1 names unknown resource 'geode' as its target robot".to_string()),
+            parse_blueprint("Factory 1: start ore; target geode; ore <- 4 ore; clay <- 2 ore")
+                .err()
+        );
+        assert_eq!(
+            Some("Factory 1 names more than one start robot".to_string()),
+            parse_blueprint("Factory 1: start ore; start clay; target clay; clay <- 2 ore").err()
+        );
+        assert_eq!(
+            Some("Blueprint 1 has no recipe for ore robots".to_string()),
+            parse_blueprint("Factory 1: start clay; target ore; clay <- 2 clay, 1 ore").err()
+        );
+        assert!(parse_blueprint("Blueprint 1: Each ore robot costs 4 ore.").is_err());
+    }
+
+    #[test]
+    fn test_generic_factory() {
+        let blueprint =
+            parse_blueprint("Factory 7: start sand; target lens; sand <- 2 sand; glass <- 3 sand; lens <- 2 sand, 2 glass")
+                .unwrap();
+        assert_eq!(vec!["sand", "glass", "lens"], blueprint.resources);
+        assert_eq!(2, blueprint.target);
+
+        let basic = blueprint.search(12, &SearchConfig::basic());
+        let full = blueprint.search(12, &SearchConfig::default());
+        assert_eq!(basic.max_geodes, full.max_geodes);
+        assert!(full.max_geodes > 0);
 
-        assert_eq!(24, blueprint.quality_level(24));
+        let blueprint =
+            parse_blueprint("Factory 2: start ore; target gem; ore <- 1 ore; gold <- 1 ore, 1 lead; gem <- 1 ore, 1 gold")
+                .unwrap();
+        assert_eq!(0, blueprint.max_geodes(24));
     }
 }
@@ -1,12 +1,11 @@
-use std::{collections::HashMap, ops};
+use std::env;
 
-use aoc2022::input::read_lines;
+use aoc2022::{input::read_lines, random::XorShift};
 
 fn main() {
-    let mut numbers = match read_lines("inputs/day20.txt")
+    let numbers = match read_lines("inputs/day20.txt")
         .map(|l| l.parse())
         .collect::<Result<Vec<isize>, _>>()
-        .map(|numbers| Numbers::new(numbers))
     {
         Ok(numbers) => numbers,
         Err(err) => {
@@ -14,89 +13,185 @@ fn main() {
             return;
         }
     };
-    let mut multiplied_numbers = &numbers * 811589153;
+    let args = env::args().collect::<Vec<_>>();
+    let unmix = args.iter().any(|a| a == "--unmix");
+    let decryption = match parse_decryption_args(
+        &args
+            .into_iter()
+            .filter(|a| a != "--unmix")
+            .collect::<Vec<_>>(),
+    ) {
+        Ok(decryption) => decryption,
+        Err(err) => {
+            println!("Invalid arguments: {}", err);
+            return;
+        }
+    };
 
-    numbers.mix_all();
+    let part1 = Decryption {
+        key: 1,
+        rounds: 1,
+        ..decryption.clone()
+    };
+    match (decrypt(&numbers, &part1), decrypt(&numbers, &decryption)) {
+        (Ok(part1), Ok(part2)) => {
+            println!("Part 1: {}", part1);
+            println!("Part 2: {}", part2);
+        }
+        (Err(err), _) | (_, Err(err)) => println!("Can't decrypt: {}", err),
+    }
 
-    println!(
-        "Part 1: {}",
-        numbers.sum_by_offsets_from_0(vec![1000, 2000, 3000])
-    );
-    for _ in 0..10 {
-        multiplied_numbers.mix_all();
+    if unmix {
+        let original = match Numbers::new(numbers).checked_mul(decryption.key) {
+            Ok(original) => original,
+            Err(err) => {
+                println!("Can't unmix: {}", err);
+                return;
+            }
+        };
+        let mut numbers = original.clone();
+        for _ in 0..decryption.rounds {
+            numbers.mix_all();
+        }
+        for _ in 0..decryption.rounds {
+            numbers.unmix_all();
+        }
+        println!(
+            "Unmixed back to the original order: {}",
+            numbers.circular_order() == original.circular_order()
+        );
     }
-    println!(
-        "Part 2: {}",
-        multiplied_numbers.sum_by_offsets_from_0(vec![1000, 2000, 3000])
-    );
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct IndexedNumber {
-    value: isize,
-    original_index: usize,
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Decryption {
+    key: isize,
+    rounds: usize,
+    offsets: Vec<usize>,
+    anchor: isize,
 }
 
-impl ops::Mul<isize> for &IndexedNumber {
-    type Output = IndexedNumber;
+impl Default for Decryption {
+    fn default() -> Decryption {
+        Decryption {
+            key: 811589153,
+            rounds: 10,
+            offsets: vec![1000, 2000, 3000],
+            anchor: 0,
+        }
+    }
+}
 
-    fn mul(self, multiplier: isize) -> IndexedNumber {
-        IndexedNumber {
-            value: self.value * multiplier,
-            original_index: self.original_index,
+fn parse_decryption_args(args: &[String]) -> Result<Decryption, String> {
+    let mut decryption = Decryption::default();
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for '{}'", arg))?;
+        let invalid = || format!("Invalid value for '{}': '{}'", arg, value);
+        match arg.as_str() {
+            "--key" => decryption.key = value.parse().map_err(|_| invalid())?,
+            "--rounds" => decryption.rounds = value.parse().map_err(|_| invalid())?,
+            "--anchor" => decryption.anchor = value.parse().map_err(|_| invalid())?,
+            "--offsets" => {
+                decryption.offsets = value
+                    .split(',')
+                    .map(|o| o.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid())?
+            }
+            _ => return Err(format!("Unknown argument '{}'", arg)),
         }
     }
+    Ok(decryption)
 }
 
+fn decrypt(numbers: &[isize], decryption: &Decryption) -> Result<isize, String> {
+    let anchor_node = find_anchor(numbers, decryption.anchor)?;
+    let mut numbers = Numbers::new(numbers.to_vec()).checked_mul(decryption.key)?;
+    for _ in 0..decryption.rounds {
+        numbers.mix_all();
+    }
+    numbers
+        .sum_by_offsets_from(anchor_node, &decryption.offsets)
+        .ok_or("Sum of the numbers at the offsets overflows".to_string())
+}
+
+fn find_anchor(numbers: &[isize], anchor: isize) -> Result<usize, String> {
+    match numbers.iter().filter(|n| **n == anchor).count() {
+        0 => Err(format!("No number {} in the list", anchor)),
+        1 => Ok(numbers.iter().position(|n| *n == anchor).unwrap()),
+        count => Err(format!(
+            "Number {} appears {} times in the list, the anchor must be unique",
+            anchor, count
+        )),
+    }
+}
+
+const NIL: usize = usize::MAX;
+
+#[derive(Clone)]
+struct Node {
+    left: usize,
+    right: usize,
+    parent: usize,
+    priority: u64,
+    size: usize,
+}
+
+#[derive(Clone)]
 struct Numbers {
     original_numbers_order: Vec<isize>,
-    indexes: HashMap<IndexedNumber, usize>,
-    numbers: HashMap<usize, IndexedNumber>,
+    nodes: Vec<Node>,
+    root: usize,
     mixed: usize,
-    original_index_of_0: usize,
 }
 
 impl Numbers {
     fn new(numbers: Vec<isize>) -> Numbers {
-        let mut indexes_per_number = HashMap::new();
-        let mut numbers_per_index = HashMap::new();
-        let mut original_index_of_0 = 0;
-
-        for (index, number) in numbers.iter().enumerate() {
-            let indexed_number = IndexedNumber {
-                value: *number,
-                original_index: index,
-            };
-            indexes_per_number.insert(indexed_number, index);
-            numbers_per_index.insert(index, indexed_number);
-            if *number == 0 {
-                original_index_of_0 = index;
-            }
-        }
+        let mut random = XorShift::new(numbers.len() as u64 + 1);
+        let nodes = (0..numbers.len())
+            .map(|_| Node {
+                left: NIL,
+                right: NIL,
+                parent: NIL,
+                priority: random.next_u64(),
+                size: 1,
+            })
+            .collect();
 
-        Numbers {
+        let mut result = Numbers {
             original_numbers_order: numbers,
-            indexes: indexes_per_number,
-            numbers: numbers_per_index,
+            nodes,
+            root: NIL,
             mixed: 0,
-            original_index_of_0,
+        };
+        for node in 0..result.len() {
+            result.root = result.merge(result.root, node);
         }
+        result.detach(result.root);
+        result
     }
 
-    fn sum_by_offsets_from_0(&self, indexes: Vec<usize>) -> isize {
-        let mut result = 0;
-        let current_index_of_0 = self
-            .indexes
-            .get(&IndexedNumber {
-                value: 0,
-                original_index: self.original_index_of_0,
-            })
-            .unwrap();
-        for offset_from_0 in &indexes {
-            result += self.numbers[&((current_index_of_0 + offset_from_0) % self.len())].value;
-        }
+    fn sum_by_offsets_from(&self, anchor_node: usize, offsets: &[usize]) -> Option<isize> {
+        let current_index_of_anchor = self.position(anchor_node);
+        offsets.iter().try_fold(0isize, |sum, offset| {
+            let node = self.node_at((current_index_of_anchor + offset) % self.len());
+            sum.checked_add(self.original_numbers_order[node])
+        })
+    }
 
-        result
+    fn checked_mul(&self, multiplier: isize) -> Result<Numbers, String> {
+        Ok(Numbers {
+            original_numbers_order: self
+                .original_numbers_order
+                .iter()
+                .map(|n| {
+                    n.checked_mul(multiplier)
+                        .ok_or(format!("Multiplying {} by key {} overflows", n, multiplier))
+                })
+                .collect::<Result<_, _>>()?,
+            ..self.clone()
+        })
     }
 
     fn mix_all(&mut self) {
@@ -107,105 +202,348 @@ impl Numbers {
     }
 
     fn mix_single_number(&mut self) {
-        let number = IndexedNumber {
-            value: self.original_numbers_order[self.mixed],
-            original_index: self.mixed,
-        };
-        if number.value == 0 {
-            self.mixed += 1;
+        let node = self.mixed;
+        let value = self.original_numbers_order[node];
+        self.mixed += 1;
+        if value == 0 || self.len() < 2 {
             return;
         }
-        let current_index = *self.indexes.get(&number).unwrap();
+
+        let current_index = self.position(node);
         let (current_index, shift_by) = if current_index == 0 {
-            let number_at_1 = *self.numbers.get(&1).unwrap();
-            self.indexes.insert(number_at_1, 0);
-            self.numbers.insert(0, number_at_1);
-            self.indexes.insert(number, 1);
-            self.numbers.insert(1, number);
-            (current_index + 1, number.value - 1)
+            self.move_node(0, 1);
+            (1, value - 1)
         } else {
-            (current_index, number.value)
+            (current_index, value)
         };
 
-        self.shift(number, current_index, shift_by);
-        self.mixed += 1;
-    }
-
-    fn shift(&mut self, number: IndexedNumber, current_index: usize, shift_by: isize) {
-        let shift_by_normalized = shift_by % (self.len() as isize - 1);
-        let shift_by_normalized = if shift_by_normalized >= 0 {
-            shift_by_normalized as usize
-        } else {
-            (shift_by_normalized + (self.len() as isize - 1)) as usize
-        };
+        let shift_by_normalized = shift_by.rem_euclid(self.len() as isize - 1) as usize;
         if shift_by_normalized == 0 {
             return;
         }
-
         let target_index = (current_index + shift_by_normalized) % self.len()
             + (current_index + shift_by_normalized) / self.len();
-        if target_index > current_index {
-            for index in (current_index + 1)..=target_index {
-                let number_to_shift_back = self.numbers.get(&index).unwrap();
-                self.indexes.insert(*number_to_shift_back, index - 1);
-                self.numbers.insert(index - 1, *number_to_shift_back);
-            }
-        } else if target_index < current_index {
-            for index in (target_index..=(current_index - 1)).rev() {
-                let number_to_shift_forward = self.numbers.get(&index).unwrap();
-                self.indexes.insert(*number_to_shift_forward, index + 1);
-                self.numbers.insert(index + 1, *number_to_shift_forward);
-            }
+        self.move_node(current_index, target_index);
+    }
+
+    fn unmix_all(&mut self) {
+        for node in (0..self.len()).rev() {
+            self.unmix_number(node);
+        }
+    }
+
+    fn unmix_number(&mut self, node: usize) {
+        let value = self.original_numbers_order[node];
+        if value == 0 || self.len() < 2 {
+            return;
         }
-        self.indexes.insert(number, target_index);
-        self.numbers.insert(target_index, number);
+        let current_index = self.position(node);
+        let target_index = (current_index as isize - value).rem_euclid(self.len() as isize - 1);
+        self.move_node(current_index, target_index as usize);
+    }
+
+    fn move_node(&mut self, from: usize, to: usize) {
+        let (before, rest) = self.split(self.root, from);
+        let (node, after) = self.split(rest, 1);
+        let without_node = self.merge(before, after);
+        let (before, after) = self.split(without_node, to);
+        let with_node = self.merge(before, node);
+        self.root = self.merge(with_node, after);
+        self.detach(self.root);
     }
 
     fn len(&self) -> usize {
         self.original_numbers_order.len()
     }
 
+    fn circular_order(&self) -> Vec<isize> {
+        let mut order = self.current_order();
+        order.rotate_left(self.position(0));
+        order
+    }
+
     fn current_order(&self) -> Vec<isize> {
-        let mut result = vec![];
+        (0..self.len())
+            .map(|index| self.original_numbers_order[self.node_at(index)])
+            .collect()
+    }
 
-        for index in 0..self.len() {
-            result.push(self.numbers.get(&index).unwrap().value);
+    fn size(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].size
         }
+    }
 
-        result
+    fn update(&mut self, node: usize) {
+        let (left, right) = (self.nodes[node].left, self.nodes[node].right);
+        self.nodes[node].size = self.size(left) + self.size(right) + 1;
+        for child in [left, right] {
+            if child != NIL {
+                self.nodes[child].parent = node;
+            }
+        }
+    }
+
+    fn detach(&mut self, node: usize) {
+        if node != NIL {
+            self.nodes[node].parent = NIL;
+        }
     }
-}
 
-impl ops::Mul<isize> for &Numbers {
-    type Output = Numbers;
+    fn split(&mut self, node: usize, count: usize) -> (usize, usize) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+        let left_size = self.size(self.nodes[node].left);
+        if count <= left_size {
+            let (left, right) = self.split(self.nodes[node].left, count);
+            self.nodes[node].left = right;
+            self.update(node);
+            self.detach(left);
+            (left, node)
+        } else {
+            let (left, right) = self.split(self.nodes[node].right, count - left_size - 1);
+            self.nodes[node].right = left;
+            self.update(node);
+            self.detach(right);
+            (node, right)
+        }
+    }
 
-    fn mul(self, multiplier: isize) -> Numbers {
-        let original_numbers_order = self
-            .original_numbers_order
-            .iter()
-            .map(|n| n * multiplier)
-            .collect();
-        let mut indexes = HashMap::new();
-        for (number, index) in self.indexes.iter() {
-            indexes.insert(number * multiplier, *index);
+    fn merge(&mut self, left: usize, right: usize) -> usize {
+        if left == NIL {
+            return right;
         }
-        let mut numbers = HashMap::new();
-        for (index, number) in self.numbers.iter() {
-            numbers.insert(*index, number * multiplier);
+        if right == NIL {
+            return left;
         }
-        Numbers {
-            original_numbers_order,
-            indexes,
-            numbers,
-            original_index_of_0: self.original_index_of_0,
-            mixed: self.mixed,
+        if self.nodes[left].priority > self.nodes[right].priority {
+            let merged = self.merge(self.nodes[left].right, right);
+            self.nodes[left].right = merged;
+            self.update(left);
+            left
+        } else {
+            let merged = self.merge(left, self.nodes[right].left);
+            self.nodes[right].left = merged;
+            self.update(right);
+            right
+        }
+    }
+
+    fn position(&self, node: usize) -> usize {
+        let mut position = self.size(self.nodes[node].left);
+        let mut current = node;
+        while self.nodes[current].parent != NIL {
+            let parent = self.nodes[current].parent;
+            if self.nodes[parent].right == current {
+                position += self.size(self.nodes[parent].left) + 1;
+            }
+            current = parent;
+        }
+        position
+    }
+
+    fn node_at(&self, index: usize) -> usize {
+        let mut node = self.root;
+        let mut index = index;
+        loop {
+            let left_size = self.size(self.nodes[node].left);
+            if index < left_size {
+                node = self.nodes[node].left;
+            } else if index == left_size {
+                return node;
+            } else {
+                index -= left_size + 1;
+                node = self.nodes[node].right;
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Numbers;
+    use aoc2022::random::XorShift;
+
+    use crate::{decrypt, parse_decryption_args, Decryption, Numbers};
+
+    mod reference {
+        use std::{collections::HashMap, ops};
+
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        struct IndexedNumber {
+            value: isize,
+            original_index: usize,
+        }
+
+        impl ops::Mul<isize> for &IndexedNumber {
+            type Output = IndexedNumber;
+
+            fn mul(self, multiplier: isize) -> IndexedNumber {
+                IndexedNumber {
+                    value: self.value * multiplier,
+                    original_index: self.original_index,
+                }
+            }
+        }
+
+        pub struct Numbers {
+            original_numbers_order: Vec<isize>,
+            indexes: HashMap<IndexedNumber, usize>,
+            numbers: HashMap<usize, IndexedNumber>,
+            pub mixed: usize,
+            original_index_of_0: usize,
+        }
+
+        impl Numbers {
+            pub fn new(numbers: Vec<isize>) -> Numbers {
+                let mut indexes_per_number = HashMap::new();
+                let mut numbers_per_index = HashMap::new();
+                let mut original_index_of_0 = 0;
+
+                for (index, number) in numbers.iter().enumerate() {
+                    let indexed_number = IndexedNumber {
+                        value: *number,
+                        original_index: index,
+                    };
+                    indexes_per_number.insert(indexed_number, index);
+                    numbers_per_index.insert(index, indexed_number);
+                    if *number == 0 {
+                        original_index_of_0 = index;
+                    }
+                }
+
+                Numbers {
+                    original_numbers_order: numbers,
+                    indexes: indexes_per_number,
+                    numbers: numbers_per_index,
+                    mixed: 0,
+                    original_index_of_0,
+                }
+            }
+
+            pub fn sum_by_offsets_from_0(&self, indexes: Vec<usize>) -> isize {
+                let mut result = 0;
+                let current_index_of_0 = self
+                    .indexes
+                    .get(&IndexedNumber {
+                        value: 0,
+                        original_index: self.original_index_of_0,
+                    })
+                    .unwrap();
+                for offset_from_0 in &indexes {
+                    result +=
+                        self.numbers[&((current_index_of_0 + offset_from_0) % self.len())].value;
+                }
+
+                result
+            }
+
+            pub fn mix_all(&mut self) {
+                for _ in 0..self.len() {
+                    self.mix_single_number();
+                }
+                self.mixed = 0;
+            }
+
+            pub fn mix_single_number(&mut self) {
+                let number = IndexedNumber {
+                    value: self.original_numbers_order[self.mixed],
+                    original_index: self.mixed,
+                };
+                if number.value == 0 {
+                    self.mixed += 1;
+                    return;
+                }
+                let current_index = *self.indexes.get(&number).unwrap();
+                let (current_index, shift_by) = if current_index == 0 {
+                    let number_at_1 = *self.numbers.get(&1).unwrap();
+                    self.indexes.insert(number_at_1, 0);
+                    self.numbers.insert(0, number_at_1);
+                    self.indexes.insert(number, 1);
+                    self.numbers.insert(1, number);
+                    (current_index + 1, number.value - 1)
+                } else {
+                    (current_index, number.value)
+                };
+
+                self.shift(number, current_index, shift_by);
+                self.mixed += 1;
+            }
+
+            fn shift(&mut self, number: IndexedNumber, current_index: usize, shift_by: isize) {
+                let shift_by_normalized = shift_by % (self.len() as isize - 1);
+                let shift_by_normalized = if shift_by_normalized >= 0 {
+                    shift_by_normalized as usize
+                } else {
+                    (shift_by_normalized + (self.len() as isize - 1)) as usize
+                };
+                if shift_by_normalized == 0 {
+                    return;
+                }
+
+                let target_index = (current_index + shift_by_normalized) % self.len()
+                    + (current_index + shift_by_normalized) / self.len();
+                if target_index > current_index {
+                    for index in (current_index + 1)..=target_index {
+                        let number_to_shift_back = self.numbers.get(&index).unwrap();
+                        self.indexes.insert(*number_to_shift_back, index - 1);
+                        self.numbers.insert(index - 1, *number_to_shift_back);
+                    }
+                } else if target_index < current_index {
+                    for index in (target_index..=(current_index - 1)).rev() {
+                        let number_to_shift_forward = self.numbers.get(&index).unwrap();
+                        self.indexes.insert(*number_to_shift_forward, index + 1);
+                        self.numbers.insert(index + 1, *number_to_shift_forward);
+                    }
+                }
+                self.indexes.insert(number, target_index);
+                self.numbers.insert(target_index, number);
+            }
+
+            pub fn len(&self) -> usize {
+                self.original_numbers_order.len()
+            }
+
+            pub fn current_order(&self) -> Vec<isize> {
+                let mut result = vec![];
+
+                for index in 0..self.len() {
+                    result.push(self.numbers.get(&index).unwrap().value);
+                }
+
+                result
+            }
+        }
+
+        impl ops::Mul<isize> for &Numbers {
+            type Output = Numbers;
+
+            fn mul(self, multiplier: isize) -> Numbers {
+                let original_numbers_order = self
+                    .original_numbers_order
+                    .iter()
+                    .map(|n| n * multiplier)
+                    .collect();
+                let mut indexes = HashMap::new();
+                for (number, index) in self.indexes.iter() {
+                    indexes.insert(number * multiplier, *index);
+                }
+                let mut numbers = HashMap::new();
+                for (index, number) in self.numbers.iter() {
+                    numbers.insert(*index, number * multiplier);
+                }
+                Numbers {
+                    original_numbers_order,
+                    indexes,
+                    numbers,
+                    original_index_of_0: self.original_index_of_0,
+                    mixed: self.mixed,
+                }
+            }
+        }
+    }
 
     #[test]
     fn examples_from_description() {
@@ -234,6 +572,143 @@ mod tests {
         numbers.mix_single_number();
         assert_eq!(vec![1, 2, -3, 4, 0, 3, -2], numbers.current_order());
 
-        assert_eq!(3, numbers.sum_by_offsets_from_0(vec![1000, 2000, 3000]));
+        assert_eq!(Some(3), numbers.sum_by_offsets_from(5, &[1000, 2000, 3000]));
+    }
+
+    #[test]
+    fn test_against_reference_implementation() {
+        let mut random = XorShift::new(20);
+        for _ in 0..200 {
+            let len = random.next_in_range(3..40) as usize;
+            let mut values = (0..len)
+                .map(|_| random.next_in_range(-100..100))
+                .filter(|v| *v != 0)
+                .collect::<Vec<_>>();
+            let zero_index = random.next_in_range(0..values.len() as isize + 1) as usize;
+            values.insert(zero_index, 0);
+
+            let mut numbers = Numbers::new(values.clone());
+            let mut expected = reference::Numbers::new(values);
+            for _ in 0..3 {
+                for _ in 0..numbers.len() {
+                    numbers.mix_single_number();
+                    expected.mix_single_number();
+                    assert_eq!(expected.current_order(), numbers.current_order());
+                }
+                numbers.mixed = 0;
+                expected.mixed = 0;
+            }
+            assert_eq!(
+                Some(expected.sum_by_offsets_from_0(vec![1000, 2000, 3000])),
+                numbers.sum_by_offsets_from(zero_index, &[1000, 2000, 3000])
+            );
+
+            let mut numbers = numbers.checked_mul(811589153).unwrap();
+            let mut expected = &expected * 811589153;
+            numbers.mix_all();
+            expected.mix_all();
+            assert_eq!(expected.current_order(), numbers.current_order());
+        }
+    }
+
+    #[test]
+    fn test_decryption() {
+        let numbers = [1, 2, -3, 3, -2, 0, 4];
+
+        let part1 = Decryption {
+            key: 1,
+            rounds: 1,
+            ..Decryption::default()
+        };
+        assert_eq!(Ok(3), decrypt(&numbers, &part1));
+        assert_eq!(Ok(1623178306), decrypt(&numbers, &Decryption::default()));
+        assert_eq!(
+            Ok(-2 + 1),
+            decrypt(
+                &numbers,
+                &Decryption {
+                    offsets: vec![1, 2],
+                    anchor: 3,
+                    ..part1.clone()
+                }
+            )
+        );
+        assert!(decrypt(
+            &numbers,
+            &Decryption {
+                anchor: 5,
+                ..part1.clone()
+            }
+        )
+        .is_err());
+        assert_eq!(
+            Err("Number 1 appears 2 times in the list, the anchor must be unique".to_string()),
+            decrypt(
+                &[1, 0, 1],
+                &Decryption {
+                    anchor: 1,
+                    ..part1.clone()
+                }
+            )
+        );
+        assert_eq!(
+            Err(format!(
+                "Multiplying -3 by key {} overflows",
+                isize::MAX / 2
+            )),
+            decrypt(
+                &numbers,
+                &Decryption {
+                    key: isize::MAX / 2,
+                    ..part1.clone()
+                }
+            )
+        );
+        assert_eq!(
+            Err("Sum of the numbers at the offsets overflows".to_string()),
+            decrypt(
+                &[0, isize::MAX, 1],
+                &Decryption {
+                    offsets: vec![1, 2],
+                    ..part1
+                }
+            )
+        );
+
+        let args = ["day20", "--key", "7", "--rounds", "2", "--offsets", "1,2"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            Ok(Decryption {
+                key: 7,
+                rounds: 2,
+                offsets: vec![1, 2],
+                anchor: 0,
+            }),
+            parse_decryption_args(&args)
+        );
+        assert!(parse_decryption_args(&["day20".to_string(), "--rounds".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_unmix() {
+        let mut random = XorShift::new(33);
+        for _ in 0..100 {
+            let len = random.next_in_range(2..30) as usize;
+            let values = (0..len)
+                .map(|_| random.next_in_range(-50..50))
+                .collect::<Vec<_>>();
+            let original = Numbers::new(values).checked_mul(811589153).unwrap();
+
+            let mut numbers = original.clone();
+            for _ in 0..3 {
+                numbers.mix_all();
+            }
+            for _ in 0..3 {
+                numbers.unmix_all();
+            }
+            assert_eq!(original.circular_order(), numbers.circular_order());
+        }
     }
 }
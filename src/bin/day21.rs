@@ -1,15 +1,45 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::{self, Display, Formatter},
+    fs,
+};
 
-use aoc2022::input::read_lines;
+use aoc2022::{input::read_lines, rational::Rational};
 use regex::Regex;
 
 #[macro_use]
 extern crate lazy_static;
 
 fn main() {
+    let mut args = env::args().collect::<Vec<_>>();
+    let dot_path = match args.iter().position(|a| a == "--dot") {
+        Some(index) if index + 1 < args.len() => Some(args.drain(index..index + 2).nth(1).unwrap()),
+        Some(_) => {
+            println!("Invalid arguments: Missing value for '--dot'");
+            return;
+        }
+        None => None,
+    };
+    let query = match parse_query_args(&args) {
+        Ok(query) => query,
+        Err(err) => {
+            println!("Invalid arguments: {}", err);
+            return;
+        }
+    };
     let lines = read_lines("inputs/day21.txt").collect::<Vec<String>>();
+    if let Some(dot_path) = dot_path {
+        match parse_jobs(&lines)
+            .and_then(|jobs| to_dot(&jobs, &query))
+            .and_then(|dot| fs::write(&dot_path, dot).map_err(|err| err.to_string()))
+        {
+            Ok(()) => println!("Dependency graph written to {}", dot_path),
+            Err(err) => println!("Can't export dependency graph: {}", err),
+        }
+    }
     let yell_job_expressions =
-        match parse_jobs(&lines).and_then(|jobs| YellJobExpressions::new(jobs)) {
+        match parse_jobs(&lines).and_then(|jobs| YellJobExpressions::new(jobs, &query)) {
             Ok(jobs) => jobs,
             Err(err) => {
                 println!("Can't parse monkey jobs or expressions: {}", err);
@@ -19,134 +49,445 @@ fn main() {
 
     println!(
         "Part 1: {:?}",
-        yell_job_expressions.root_expression_value_as_in_first_part()
-    );
-    println!(
-        "Part 2: {:?}",
-        yell_job_expressions.solve_for_x_so_that_root_expression_matches()
+        yell_job_expressions.target_value_as_in_first_part()
     );
+    println!("Part 2: {:?}", yell_job_expressions.solve_for_unknown());
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Goal {
+    OperandsEqual,
+    Value(isize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Query {
+    unknown: String,
+    target: String,
+    goal: Goal,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Query {
+            unknown: "humn".to_string(),
+            target: "root".to_string(),
+            goal: Goal::OperandsEqual,
+        }
+    }
+}
+
+fn parse_query_args(args: &[String]) -> Result<Query, String> {
+    let mut query = Query::default();
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for '{}'", arg))?;
+        match arg.as_str() {
+            "--unknown" => query.unknown = value.clone(),
+            "--target" => query.target = value.clone(),
+            "--equals" => {
+                query.goal = match value.as_str() {
+                    "operands" => Goal::OperandsEqual,
+                    _ => Goal::Value(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid value for '{}': '{}'", arg, value))?,
+                    ),
+                }
+            }
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+    }
+    Ok(query)
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Operation {
     Plus,
     Minus,
     Multiplication,
     Division,
+    Modulo,
+    Power,
 }
 
 impl Operation {
-    fn apply(self, left: isize, right: isize) -> Result<isize, String> {
+    fn apply(self, left: Rational, right: Rational) -> Result<Rational, String> {
+        let overflow = || format!("{} {:?} {} overflows", left, self, right);
         match self {
-            Operation::Plus => Ok(left + right),
-            Operation::Minus => Ok(left - right),
-            Operation::Multiplication => Ok(left * right),
+            Operation::Plus => left.checked_add(right).ok_or_else(overflow),
+            Operation::Minus => left.checked_sub(right).ok_or_else(overflow),
+            Operation::Multiplication => left.checked_mul(right).ok_or_else(overflow),
             Operation::Division => {
-                if right == 0 {
+                if right.is_zero() {
                     Err("Division by zero".to_string())
-                } else if left % right != 0 {
-                    Err(format!(
-                        "Can't divide {} by {} without remainder",
-                        left, right
-                    ))
                 } else {
-                    Ok(left / right)
+                    left.checked_div(right).ok_or_else(overflow)
                 }
             }
+            Operation::Modulo => match (left.to_integer(), right.to_integer()) {
+                (_, Some(0)) => Err("Modulo by zero".to_string()),
+                (Some(left), Some(right)) => left
+                    .checked_rem(right)
+                    .map(Rational::integer)
+                    .ok_or_else(overflow),
+                _ => Err(format!(
+                    "Can't take {} modulo {} of non-integers",
+                    left, right
+                )),
+            },
+            Operation::Power => match right.to_integer().map(i32::try_from) {
+                Some(Ok(exponent)) => left.checked_pow(exponent).ok_or_else(overflow),
+                _ => Err(format!(
+                    "Can't raise {} to non-integer power {}",
+                    left, right
+                )),
+            },
         }
     }
 
-    fn opposite(self) -> Operation {
+    fn symbol(self) -> char {
         match self {
-            Operation::Plus => Operation::Minus,
-            Operation::Minus => Operation::Plus,
-            Operation::Multiplication => Operation::Division,
-            Operation::Division => Operation::Multiplication,
+            Operation::Plus => '+',
+            Operation::Minus => '-',
+            Operation::Multiplication => '*',
+            Operation::Division => '/',
+            Operation::Modulo => '%',
+            Operation::Power => '^',
+        }
+    }
+
+    fn from_char(char: char) -> Option<Operation> {
+        match char {
+            '+' => Some(Operation::Plus),
+            '-' => Some(Operation::Minus),
+            '*' => Some(Operation::Multiplication),
+            '/' => Some(Operation::Division),
+            '%' => Some(Operation::Modulo),
+            '^' => Some(Operation::Power),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum YellJob<'a> {
     Constant(isize),
+    Monkey(&'a str),
+    Negation(Box<YellJob<'a>>),
     BinaryExpression {
         operation: Operation,
-        left_monkey: &'a str,
-        right_monkey: &'a str,
+        left: Box<YellJob<'a>>,
+        right: Box<YellJob<'a>>,
     },
 }
 
-fn parse_jobs<'a>(lines: &'a Vec<String>) -> Result<HashMap<&'a str, YellJob<'a>>, String> {
-    lines.iter().map(|l| parse_job(l)).collect()
+impl<'a> YellJob<'a> {
+    fn referenced_monkeys(&self) -> Vec<&'a str> {
+        match self {
+            YellJob::Constant(_) => vec![],
+            YellJob::Monkey(monkey) => vec![monkey],
+            YellJob::Negation(job) => job.referenced_monkeys(),
+            YellJob::BinaryExpression { left, right, .. } => {
+                let mut monkeys = left.referenced_monkeys();
+                monkeys.extend(right.referenced_monkeys());
+                monkeys
+            }
+        }
+    }
 }
 
-fn parse_job<'a>(line: &'a str) -> Result<(&'a str, YellJob<'a>), String> {
-    lazy_static! {
-        static ref CONSTANT_JOB_RE: Regex = Regex::new(r"^([a-z]+): (\d+)$").unwrap();
-        static ref BINARY_EXPRESSION_JOB_RE: Regex =
-            Regex::new(r"^([a-z]+): ([a-z]+) (\+|\*|/|\-) ([a-z]+)$").unwrap();
-    }
-    if let Some(captures) = CONSTANT_JOB_RE.captures(line) {
-        return match (
-            captures.get(1).unwrap().as_str(),
-            captures.get(2).unwrap().as_str().parse(),
-        ) {
-            (monkey, Ok(constant_value)) => Ok((monkey, YellJob::Constant(constant_value))),
-            _ => Err(format!("Can't parse monkey job out of '{}'", line)),
+impl Display for YellJob<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let operand = |job: &YellJob| match job {
+            YellJob::BinaryExpression { .. } => format!("({})", job),
+            _ => job.to_string(),
         };
+        match self {
+            YellJob::Constant(value) => write!(f, "{}", value),
+            YellJob::Monkey(monkey) => write!(f, "{}", monkey),
+            YellJob::Negation(job) => write!(f, "-{}", operand(job)),
+            YellJob::BinaryExpression {
+                operation,
+                left,
+                right,
+            } => write!(
+                f,
+                "{} {} {}",
+                operand(left),
+                operation.symbol(),
+                operand(right)
+            ),
+        }
+    }
+}
+
+fn parse_jobs(lines: &[String]) -> Result<HashMap<&str, YellJob<'_>>, String> {
+    let mut jobs = HashMap::new();
+    for line in lines {
+        let (monkey, job) = parse_job(line)?;
+        if jobs.insert(monkey, job).is_some() {
+            return Err(format!("Monkey {} has more than one job", monkey));
+        }
+    }
+    Ok(jobs)
+}
+
+fn parse_job(line: &str) -> Result<(&str, YellJob<'_>), String> {
+    lazy_static! {
+        static ref JOB_RE: Regex = Regex::new(r"^([a-z]+): (.+)$").unwrap();
+    }
+    let captures = JOB_RE
+        .captures(line)
+        .ok_or(format!("Can't parse monkey job out of '{}'", line))?;
+    let monkey = captures.get(1).unwrap().as_str();
+    let tokens = tokenize(captures.get(2).unwrap().as_str())
+        .map_err(|err| format!("{}, whole line: {}", err, line))?;
+    let mut parser = JobParser {
+        tokens: &tokens,
+        position: 0,
     };
-    if let Some(captures) = BINARY_EXPRESSION_JOB_RE.captures(line) {
-        let (monkey, left_monkey, operation_str, right_monkey) = (
-            captures.get(1).unwrap().as_str(),
-            captures.get(2).unwrap().as_str(),
-            captures.get(3).unwrap().as_str(),
-            captures.get(4).unwrap().as_str(),
-        );
-        let operation = match operation_str {
-            "+" => Operation::Plus,
-            "-" => Operation::Minus,
-            "*" => Operation::Multiplication,
-            "/" => Operation::Division,
-            _ => {
+    let job = parser
+        .parse_expression()
+        .map_err(|err| format!("{}, whole line: {}", err, line))?;
+    if parser.position != tokens.len() {
+        return Err(format!(
+            "Unexpected {:?} in monkey job, whole line: {}",
+            tokens[parser.position], line
+        ));
+    }
+    Ok((monkey, job))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Number(isize),
+    Monkey(&'a str),
+    Operator(char),
+    OpeningParenthesis,
+    ClosingParenthesis,
+}
+
+fn tokenize(str: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = vec![];
+    let mut chars = str.char_indices().peekable();
+    while let Some((start, char)) = chars.next() {
+        match char {
+            ' ' => {}
+            '(' => tokens.push(Token::OpeningParenthesis),
+            ')' => tokens.push(Token::ClosingParenthesis),
+            '0'..='9' | 'a'..='z' => {
+                let mut end = start + 1;
+                while let Some((index, next)) = chars.peek() {
+                    if !next.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    end = index + 1;
+                    chars.next();
+                }
+                let word = &str[start..end];
+                tokens.push(if char.is_ascii_digit() {
+                    Token::Number(
+                        word.parse()
+                            .map_err(|_| format!("Can't parse number out of '{}'", word))?,
+                    )
+                } else {
+                    Token::Monkey(word)
+                });
+            }
+            operator if Operation::from_char(operator).is_some() => {
+                tokens.push(Token::Operator(operator))
+            }
+            unexpected => return Err(format!("Unexpected char '{}'", unexpected)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct JobParser<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    position: usize,
+}
+
+impl<'t, 'a> JobParser<'t, 'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).cloned()
+    }
+
+    fn parse_binary(
+        &mut self,
+        operators: &[char],
+        parse_operand: fn(&mut Self) -> Result<YellJob<'a>, String>,
+    ) -> Result<YellJob<'a>, String> {
+        let mut left = parse_operand(self)?;
+        while let Some(Token::Operator(operator)) = self.peek() {
+            if !operators.contains(&operator) {
+                break;
+            }
+            self.position += 1;
+            left = YellJob::BinaryExpression {
+                operation: Operation::from_char(operator).unwrap(),
+                left: Box::new(left),
+                right: Box::new(parse_operand(self)?),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_expression(&mut self) -> Result<YellJob<'a>, String> {
+        self.parse_binary(&['+', '-'], Self::parse_term)
+    }
+
+    fn parse_term(&mut self) -> Result<YellJob<'a>, String> {
+        self.parse_binary(&['*', '/', '%'], Self::parse_unary)
+    }
+
+    fn parse_unary(&mut self) -> Result<YellJob<'a>, String> {
+        if self.peek() == Some(Token::Operator('-')) {
+            self.position += 1;
+            return Ok(YellJob::Negation(Box::new(self.parse_unary()?)));
+        }
+        self.parse_power()
+    }
+
+    fn parse_power(&mut self) -> Result<YellJob<'a>, String> {
+        let base = self.parse_primary()?;
+        if self.peek() == Some(Token::Operator('^')) {
+            self.position += 1;
+            return Ok(YellJob::BinaryExpression {
+                operation: Operation::Power,
+                left: Box::new(base),
+                right: Box::new(self.parse_unary()?),
+            });
+        }
+        Ok(base)
+    }
+
+    fn parse_primary(&mut self) -> Result<YellJob<'a>, String> {
+        let token = self.peek();
+        self.position += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(YellJob::Constant(value)),
+            Some(Token::Monkey(monkey)) => Ok(YellJob::Monkey(monkey)),
+            Some(Token::OpeningParenthesis) => {
+                let expression = self.parse_expression()?;
+                match self.peek() {
+                    Some(Token::ClosingParenthesis) => {
+                        self.position += 1;
+                        Ok(expression)
+                    }
+                    _ => Err("Expected closing parenthesis".to_string()),
+                }
+            }
+            Some(unexpected) => Err(format!("Unexpected {:?}", unexpected)),
+            None => Err("Unexpected end of monkey job".to_string()),
+        }
+    }
+}
+
+fn evaluation_order<'a>(
+    jobs: &HashMap<&'a str, YellJob<'a>>,
+    root: &'a str,
+) -> Result<Vec<&'a str>, String> {
+    let mut order = vec![];
+    let mut done = HashSet::new();
+    let mut path: Vec<&str> = vec![];
+    let mut stack = vec![(root, None, false)];
+
+    while let Some((monkey, referenced_by, expanded)) = stack.pop() {
+        if expanded {
+            path.pop();
+            done.insert(monkey);
+            order.push(monkey);
+            continue;
+        }
+        if done.contains(monkey) {
+            continue;
+        }
+        if let Some(start) = path.iter().position(|m| *m == monkey) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(monkey);
+            return Err(format!("Monkeys form a cycle: {}", cycle.join(" -> ")));
+        }
+        let job = match (jobs.get(monkey), referenced_by) {
+            (Some(job), _) => job,
+            (None, Some(referenced_by)) => {
                 return Err(format!(
-                    "Can't parse monkey job operation out of '{}', whole line: {}",
-                    operation_str, line
+                    "Couldn't find a job for monkey {}, referenced by {}",
+                    monkey, referenced_by
                 ))
             }
+            (None, None) => return Err(format!("Couldn't find a job for monkey {}", monkey)),
         };
-        return Ok((
-            monkey,
-            YellJob::BinaryExpression {
-                left_monkey,
-                operation,
-                right_monkey,
-            },
-        ));
-    };
-    Err(format!("Can't parse monkey job out of '{}'", line))
+        path.push(monkey);
+        stack.push((monkey, referenced_by, true));
+        for dependency in job.referenced_monkeys().into_iter().rev() {
+            stack.push((dependency, Some(monkey), false));
+        }
+    }
+
+    Ok(order)
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 enum Expression {
-    Constant(isize),
-    X,
+    Constant(Rational),
+    Linear {
+        coefficient: Rational,
+        constant: Rational,
+    },
     OperationAppliedToExpressionAndConstant {
         expression_with_x: Box<Expression>,
         operation: Operation,
-        constant_value: isize,
+        constant_value: Rational,
     },
     OperationAppliedToConstantAndExpression {
-        constant_value: isize,
+        constant_value: Rational,
         operation: Operation,
         expression_with_x: Box<Expression>,
     },
 }
 
 impl Expression {
-    fn value_if_x_is(&self, x_value: isize) -> Result<isize, String> {
+    fn x() -> Expression {
+        Expression::Linear {
+            coefficient: Rational::one(),
+            constant: Rational::zero(),
+        }
+    }
+
+    fn linear(coefficient: Rational, constant: Rational) -> Expression {
+        if coefficient.is_zero() {
+            Expression::Constant(constant)
+        } else {
+            Expression::Linear {
+                coefficient,
+                constant,
+            }
+        }
+    }
+
+    fn as_linear(&self) -> Option<(Rational, Rational)> {
+        match self {
+            Expression::Constant(constant) => Some((Rational::zero(), *constant)),
+            Expression::Linear {
+                coefficient,
+                constant,
+            } => Some((*coefficient, *constant)),
+            _ => None,
+        }
+    }
+
+    fn value_if_x_is(&self, x_value: Rational) -> Result<Rational, String> {
         match self {
             Expression::Constant(constant_value) => Ok(*constant_value),
-            Expression::X => Ok(x_value),
+            Expression::Linear {
+                coefficient,
+                constant,
+            } => Operation::Plus.apply(
+                Operation::Multiplication.apply(*coefficient, x_value)?,
+                *constant,
+            ),
             Expression::OperationAppliedToExpressionAndConstant {
                 expression_with_x,
                 operation,
@@ -162,230 +503,765 @@ impl Expression {
 }
 
 struct YellJobExpressions {
-    root_expression: Expression,
-    x_value_as_in_first_part: isize,
+    query: Query,
+    target_expression: Expression,
+    target_operands: Option<(Expression, Expression)>,
+    unknown_value_as_in_first_part: Rational,
 }
 
 impl YellJobExpressions {
-    fn new(jobs: HashMap<&str, YellJob>) -> Result<YellJobExpressions, String> {
-        let x_value_as_in_first_part = match jobs.get("humn") {
-            Some(YellJob::Constant(value)) => *value,
-            _ => return Err("Expected humn monkey to have a constant job".to_string()),
+    fn new(jobs: HashMap<&str, YellJob>, query: &Query) -> Result<YellJobExpressions, String> {
+        let unknown = match jobs.get_key_value(query.unknown.as_str()) {
+            Some((unknown, _)) => *unknown,
+            None => return Err(format!("Couldn't find a job for monkey {}", query.unknown)),
         };
+        let target = match jobs.get_key_value(query.target.as_str()) {
+            Some((target, _)) => *target,
+            None => return Err(format!("Couldn't find a job for monkey {}", query.target)),
+        };
+
+        let unknown_value_as_in_first_part =
+            match Self::calculate_expressions_per_monkey(&jobs, unknown, None)?.get(unknown) {
+                Some(Expression::Constant(value)) => *value,
+                _ => return Err(format!("Couldn't calculate value of monkey {}", unknown)),
+            };
 
-        let expressions_per_monkey = Self::calculate_expressions_per_monkey(jobs)?;
+        let expressions_per_monkey =
+            Self::calculate_expressions_per_monkey(&jobs, target, Some(unknown))?;
 
-        let root_expression = match expressions_per_monkey.get("root") {
+        let target_expression = match expressions_per_monkey.get(target) {
             Some(expression) => expression,
-            _ => return Err("Couldn't find root monkey expression".to_string()),
+            _ => return Err(format!("Couldn't find monkey {} expression", target)),
+        };
+        let target_operands = match jobs.get(target) {
+            Some(YellJob::BinaryExpression { left, right, .. }) => Some((
+                Self::job_expression(left, &expressions_per_monkey)?,
+                Self::job_expression(right, &expressions_per_monkey)?,
+            )),
+            _ => None,
         };
 
         Ok(YellJobExpressions {
-            root_expression: root_expression.clone(),
-            x_value_as_in_first_part,
+            query: query.clone(),
+            target_expression: target_expression.clone(),
+            target_operands,
+            unknown_value_as_in_first_part,
         })
     }
 
     fn calculate_expressions_per_monkey<'a>(
-        jobs: HashMap<&'a str, YellJob<'a>>,
+        jobs: &HashMap<&'a str, YellJob<'a>>,
+        target: &'a str,
+        unknown: Option<&str>,
     ) -> Result<HashMap<&'a str, Expression>, String> {
         let mut calculated = HashMap::new();
-        let mut stack = vec![];
-
-        stack.push("root");
 
-        while let Some(monkey) = stack.pop() {
-            if monkey == "humn" {
-                calculated.insert(monkey, Expression::X);
-                continue;
-            }
-            let job = match jobs.get(monkey) {
-                Some(job) => job,
-                None => return Err(format!("Couldn't find a job for monkey {}", monkey)),
-            };
-            match job {
-                YellJob::Constant(value) => {
-                    calculated.insert(monkey, Expression::Constant(*value));
-                }
-                YellJob::BinaryExpression {
-                    operation: yell_job_operation,
-                    left_monkey,
-                    right_monkey,
-                } => match (calculated.get(left_monkey), calculated.get(right_monkey)) {
-                    (None, None) => {
-                        stack.push(monkey);
-                        stack.push(&left_monkey);
-                        stack.push(&right_monkey);
-                    }
-                    (None, Some(_)) => {
-                        stack.push(monkey);
-                        stack.push(&left_monkey);
-                    }
-                    (Some(_), None) => {
-                        stack.push(monkey);
-                        stack.push(&right_monkey);
-                    }
-                    (Some(left_expression), Some(right_expression)) => {
-                        calculated.insert(
-                            monkey,
-                            Self::calculate_expression(
-                                left_expression,
-                                right_expression,
-                                *yell_job_operation,
-                            )?,
-                        );
-                    }
-                },
+        for monkey in evaluation_order(jobs, target)? {
+            let expression = if Some(monkey) == unknown {
+                Expression::x()
+            } else {
+                Self::job_expression(&jobs[monkey], &calculated)?
             };
+            calculated.insert(monkey, expression);
         }
 
         Ok(calculated)
     }
 
+    fn job_expression(
+        job: &YellJob,
+        calculated: &HashMap<&str, Expression>,
+    ) -> Result<Expression, String> {
+        match job {
+            YellJob::Constant(value) => Ok(Expression::Constant(Rational::from(*value))),
+            YellJob::Monkey(monkey) => Ok(calculated[monkey].clone()),
+            YellJob::Negation(job) => Self::calculate_expression(
+                &Expression::Constant(Rational::zero()),
+                &Self::job_expression(job, calculated)?,
+                Operation::Minus,
+            ),
+            YellJob::BinaryExpression {
+                operation,
+                left,
+                right,
+            } => Self::calculate_expression(
+                &Self::job_expression(left, calculated)?,
+                &Self::job_expression(right, calculated)?,
+                *operation,
+            ),
+        }
+    }
+
     fn calculate_expression(
         left_expression: &Expression,
         right_expression: &Expression,
         yell_job_operation: Operation,
     ) -> Result<Expression, String> {
+        if let (
+            Some((left_coefficient, left_constant)),
+            Some((right_coefficient, right_constant)),
+        ) = (left_expression.as_linear(), right_expression.as_linear())
+        {
+            let linear = match yell_job_operation {
+                Operation::Plus | Operation::Minus => Some(Expression::linear(
+                    yell_job_operation.apply(left_coefficient, right_coefficient)?,
+                    yell_job_operation.apply(left_constant, right_constant)?,
+                )),
+                Operation::Multiplication if right_coefficient.is_zero() => {
+                    Some(Expression::linear(
+                        Operation::Multiplication.apply(left_coefficient, right_constant)?,
+                        Operation::Multiplication.apply(left_constant, right_constant)?,
+                    ))
+                }
+                Operation::Multiplication if left_coefficient.is_zero() => {
+                    Some(Expression::linear(
+                        Operation::Multiplication.apply(left_constant, right_coefficient)?,
+                        Operation::Multiplication.apply(left_constant, right_constant)?,
+                    ))
+                }
+                Operation::Division if right_coefficient.is_zero() => Some(Expression::linear(
+                    Operation::Division.apply(left_coefficient, right_constant)?,
+                    Operation::Division.apply(left_constant, right_constant)?,
+                )),
+                _ if left_coefficient.is_zero() && right_coefficient.is_zero() => Some(
+                    Expression::Constant(yell_job_operation.apply(left_constant, right_constant)?),
+                ),
+                _ => None,
+            };
+            if let Some(linear) = linear {
+                return Ok(linear);
+            }
+        }
+
         let expression = match (left_expression, right_expression) {
-            (
-                Expression::Constant(left_constant_value),
-                Expression::Constant(right_constant_value),
-            ) => Expression::Constant(
-                yell_job_operation.apply(*left_constant_value, *right_constant_value)?,
-            ),
-            (Expression::Constant(constant), Expression::X) => {
+            (Expression::Constant(left_constant_value), right_expression_with_x) => {
                 Expression::OperationAppliedToConstantAndExpression {
-                    constant_value: *constant,
+                    constant_value: *left_constant_value,
                     operation: yell_job_operation,
-                    expression_with_x: Box::new(Expression::X),
+                    expression_with_x: Box::new(right_expression_with_x.clone()),
                 }
             }
-            (
-                Expression::Constant(left_constant_value),
-                right_expression_with_x @ Expression::OperationAppliedToExpressionAndConstant {
-                    expression_with_x: _,
-                    operation: _,
-                    constant_value: _,
-                },
-            ) => Expression::OperationAppliedToConstantAndExpression {
-                constant_value: *left_constant_value,
-                operation: yell_job_operation,
-                expression_with_x: Box::new(right_expression_with_x.clone()),
-            },
-            (
-                Expression::Constant(left_constant_value),
-                right_expression_with_x @ Expression::OperationAppliedToConstantAndExpression {
-                    constant_value: _,
-                    operation: _,
-                    expression_with_x: _,
-                },
-            ) => Expression::OperationAppliedToConstantAndExpression {
-                constant_value: *left_constant_value,
-                operation: yell_job_operation,
-                expression_with_x: Box::new(right_expression_with_x.clone()),
-            },
-            (Expression::X, Expression::Constant(right_constant_value)) => {
+            (left_expression_with_x, Expression::Constant(right_constant_value)) => {
                 Expression::OperationAppliedToExpressionAndConstant {
-                    expression_with_x: Box::new(Expression::X),
+                    expression_with_x: Box::new(left_expression_with_x.clone()),
                     operation: yell_job_operation,
                     constant_value: *right_constant_value,
                 }
             }
-            (
-                left_expression_with_x @ Expression::OperationAppliedToExpressionAndConstant {
-                    expression_with_x: _,
-                    operation: _,
-                    constant_value: _,
-                },
-                Expression::Constant(right_constant_value),
-            ) => Expression::OperationAppliedToExpressionAndConstant {
-                expression_with_x: Box::new(left_expression_with_x.clone()),
-                operation: yell_job_operation,
-                constant_value: *right_constant_value,
-            },
-            (
-                left_expression_with_x @ Expression::OperationAppliedToConstantAndExpression {
-                    constant_value: _,
-                    operation: _,
-                    expression_with_x: _,
-                },
-                Expression::Constant(right_constant_value),
-            ) => Expression::OperationAppliedToExpressionAndConstant {
-                expression_with_x: Box::new(left_expression_with_x.clone()),
-                operation: yell_job_operation,
-                constant_value: *right_constant_value,
-            },
-            _ => return Err("Monkey can't have x in both parts of binary expression".to_string()),
+            _ => {
+                return Err(format!(
+                "Monkey can't have x in both parts of binary expression {:?} unless it's linear",
+                yell_job_operation
+            ))
+            }
         };
         Ok(expression)
     }
 
-    fn root_expression_value_as_in_first_part(&self) -> Result<isize, String> {
-        self.root_expression
-            .value_if_x_is(self.x_value_as_in_first_part)
+    fn target_value_as_in_first_part(&self) -> Result<isize, String> {
+        let value = self
+            .target_expression
+            .value_if_x_is(self.unknown_value_as_in_first_part)?;
+        to_isize(value).map_err(|_| {
+            format!(
+                "Monkey {} yells non-integer value {}",
+                self.query.target, value
+            )
+        })
     }
 
-    fn solve_for_x_so_that_root_expression_matches(&self) -> Result<isize, String> {
-        match &self.root_expression {
-            Expression::OperationAppliedToExpressionAndConstant {
-                expression_with_x,
-                operation: _,
-                constant_value,
-            } => Self::solve_for_x(expression_with_x, *constant_value),
-            Expression::OperationAppliedToConstantAndExpression {
-                constant_value,
-                operation: _,
-                expression_with_x,
-            } => Self::solve_for_x(expression_with_x, *constant_value),
-            Expression::Constant(_) => return Err("Expected root expression to be binary, where one operand contains x: found constant".to_string()),
-            Expression::X => return Err("Expected root expression to be binary, where one operand contains x: found x".to_string()),
+    fn solve_for_unknown(&self) -> Result<isize, String> {
+        if let Expression::Constant(_) = self.target_expression {
+            return Err(format!(
+                "Monkey {} doesn't depend on monkey {}",
+                self.query.target, self.query.unknown
+            ));
+        }
+        let x = match self.query.goal {
+            Goal::Value(value) => {
+                Self::solve_for_x(&self.target_expression, Rational::from(value))?
+            }
+            Goal::OperandsEqual => self.solve_for_equal_operands()?,
+        };
+        to_isize(x)
+            .map_err(|_| format!("Solution {} = {} is not an integer", self.query.unknown, x))
+    }
+
+    fn solve_for_equal_operands(&self) -> Result<Rational, String> {
+        let (left, right) = match &self.target_operands {
+            Some(target_operands) => target_operands,
+            None => {
+                return Err(format!(
+                    "Expected {} job to be a binary expression to compare operands",
+                    self.query.target
+                ))
+            }
+        };
+        match (left, right) {
+            (Expression::Constant(constant_value), expression_with_x)
+            | (expression_with_x, Expression::Constant(constant_value)) => {
+                Self::solve_for_x(expression_with_x, *constant_value)
+            }
+            _ => match (left.as_linear(), right.as_linear()) {
+                (
+                    Some((left_coefficient, left_constant)),
+                    Some((right_coefficient, right_constant)),
+                ) => {
+                    let coefficient =
+                        Operation::Minus.apply(left_coefficient, right_coefficient)?;
+                    if coefficient.is_zero() {
+                        return Err(format!(
+                            "Monkey {} operands have no unique solution for {}",
+                            self.query.target, self.query.unknown
+                        ));
+                    }
+                    Self::solve_for_x(
+                        &Expression::linear(coefficient, Rational::zero()),
+                        Operation::Minus.apply(right_constant, left_constant)?,
+                    )
+                }
+                _ => Err(format!(
+                    "Both monkey {} operands depend on {} non-linearly",
+                    self.query.target, self.query.unknown
+                )),
+            },
         }
     }
 
     fn solve_for_x(
         expression_with_x: &Expression,
-        expression_value: isize,
-    ) -> Result<isize, String> {
+        expression_value: Rational,
+    ) -> Result<Rational, String> {
         let mut current_expression = expression_with_x;
         let mut current_value = expression_value;
         loop {
             match &current_expression {
-                Expression::X => return Ok(current_value),
+                Expression::Linear {
+                    coefficient,
+                    constant,
+                } => {
+                    return Operation::Division.apply(
+                        Operation::Minus.apply(current_value, *constant)?,
+                        *coefficient,
+                    )
+                }
                 Expression::OperationAppliedToExpressionAndConstant {
                     expression_with_x,
                     operation,
                     constant_value,
                 } => {
                     current_expression = expression_with_x;
-                    current_value = operation.opposite().apply(current_value, *constant_value)?;
+                    current_value = match operation {
+                        Operation::Plus => {
+                            Operation::Minus.apply(current_value, *constant_value)?
+                        }
+                        Operation::Minus => {
+                            Operation::Plus.apply(current_value, *constant_value)?
+                        }
+                        Operation::Multiplication => {
+                            Operation::Division.apply(current_value, *constant_value)?
+                        }
+                        Operation::Division => {
+                            Operation::Multiplication.apply(current_value, *constant_value)?
+                        }
+                        Operation::Modulo => {
+                            return Err("Can't solve for x under modulo".to_string())
+                        }
+                        Operation::Power => rational_root(current_value, *constant_value)?,
+                    };
                 }
                 Expression::OperationAppliedToConstantAndExpression {
                     constant_value,
                     operation,
                     expression_with_x,
-                } => match operation {
-                    Operation::Plus => {
-                        current_expression = expression_with_x;
-                        current_value = Operation::Minus.apply(current_value, *constant_value)?;
-                    }
-                    Operation::Minus => {
-                        current_expression = expression_with_x;
-                        current_value = Operation::Minus.apply(*constant_value, current_value)?;
-                    }
-                    Operation::Multiplication => {
-                        current_expression = expression_with_x;
-                        current_value =
-                            Operation::Division.apply(current_value, *constant_value)?;
-                    }
-                    Operation::Division => {
-                        current_expression = expression_with_x;
-                        current_value =
-                            Operation::Division.apply(*constant_value, current_value)?;
-                    }
-                },
-                Expression::Constant(_) => panic!("This shouldn't happen"),
+                } => {
+                    current_expression = expression_with_x;
+                    current_value = match operation {
+                        Operation::Plus => {
+                            Operation::Minus.apply(current_value, *constant_value)?
+                        }
+                        Operation::Minus => {
+                            Operation::Minus.apply(*constant_value, current_value)?
+                        }
+                        Operation::Multiplication => {
+                            Operation::Division.apply(current_value, *constant_value)?
+                        }
+                        Operation::Division => {
+                            Operation::Division.apply(*constant_value, current_value)?
+                        }
+                        Operation::Modulo => {
+                            return Err("Can't solve for x under modulo".to_string())
+                        }
+                        Operation::Power => return Err("Can't solve for x in exponent".to_string()),
+                    };
+                }
+                Expression::Constant(_) => {
+                    return Err("Expected expression to depend on x".to_string())
+                }
+            }
+        }
+    }
+}
+
+fn monkey_values<'a>(
+    jobs: &HashMap<&'a str, YellJob<'a>>,
+) -> Result<HashMap<&'a str, Rational>, String> {
+    let mut calculated = HashMap::new();
+    let mut monkeys = jobs.keys().copied().collect::<Vec<_>>();
+    monkeys.sort();
+    for monkey in monkeys {
+        if calculated.contains_key(monkey) {
+            continue;
+        }
+        for monkey in evaluation_order(jobs, monkey)? {
+            if !calculated.contains_key(monkey) {
+                let expression = YellJobExpressions::job_expression(&jobs[monkey], &calculated)?;
+                calculated.insert(monkey, expression);
+            }
+        }
+    }
+    calculated
+        .into_iter()
+        .map(|(monkey, expression)| match expression {
+            Expression::Constant(value) => Ok((monkey, value)),
+            _ => Err(format!("Couldn't calculate value of monkey {}", monkey)),
+        })
+        .collect()
+}
+
+fn path_monkeys<'a>(
+    jobs: &HashMap<&'a str, YellJob<'a>>,
+    from: &str,
+    to: &'a str,
+) -> Result<HashSet<&'a str>, String> {
+    let mut on_path = HashSet::new();
+    if !jobs.contains_key(to) {
+        return Ok(on_path);
+    }
+    for monkey in evaluation_order(jobs, to)? {
+        if monkey == from
+            || jobs[monkey]
+                .referenced_monkeys()
+                .iter()
+                .any(|referenced| on_path.contains(referenced))
+        {
+            on_path.insert(monkey);
+        }
+    }
+    Ok(on_path)
+}
+
+fn to_dot(jobs: &HashMap<&str, YellJob>, query: &Query) -> Result<String, String> {
+    let values = monkey_values(jobs)?;
+    let on_path = path_monkeys(jobs, &query.unknown, &query.target)?;
+    let highlight = "color=red, penwidth=2";
+
+    let mut monkeys = jobs.keys().copied().collect::<Vec<_>>();
+    monkeys.sort();
+
+    let mut dot = String::from("digraph monkeys {\n    node [shape=box];\n");
+    for monkey in monkeys.iter() {
+        let job = match &jobs[monkey] {
+            YellJob::Constant(_) => String::new(),
+            job => format!("\\n{}", job),
+        };
+        dot.push_str(&format!(
+            "    \"{}\" [label=\"{}{}\\n= {}\"{}];\n",
+            monkey,
+            monkey,
+            job,
+            values[monkey],
+            if on_path.contains(monkey) {
+                format!(", {}", highlight)
+            } else {
+                String::new()
             }
+        ));
+    }
+    for monkey in monkeys.iter() {
+        let mut referenced_monkeys = jobs[monkey].referenced_monkeys();
+        referenced_monkeys.sort();
+        referenced_monkeys.dedup();
+        for referenced in referenced_monkeys {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                referenced,
+                monkey,
+                if on_path.contains(referenced) && on_path.contains(monkey) {
+                    format!(" [{}]", highlight)
+                } else {
+                    String::new()
+                }
+            ));
         }
     }
+    dot.push_str("}\n");
+    Ok(dot)
+}
+
+fn to_isize(value: Rational) -> Result<isize, String> {
+    value
+        .to_integer()
+        .and_then(|value| isize::try_from(value).ok())
+        .ok_or(format!("{} is not an integer", value))
+}
+
+fn rational_root(value: Rational, exponent: Rational) -> Result<Rational, String> {
+    let no_root = || format!("{} has no rational root of degree {}", value, exponent);
+    let exponent = match exponent.to_integer().map(u32::try_from) {
+        Some(Ok(exponent)) if exponent > 0 => exponent,
+        _ => return Err(no_root()),
+    };
+    if value.numerator() < 0 && exponent % 2 == 0 {
+        return Err(no_root());
+    }
+    let root = match (
+        integer_root(value.numerator().unsigned_abs(), exponent),
+        integer_root(value.denominator().unsigned_abs(), exponent),
+    ) {
+        (Some(numerator), Some(denominator)) => i128::try_from(numerator)
+            .ok()
+            .and_then(|numerator| {
+                Rational::new(value.numerator().signum() * numerator, denominator as i128)
+            })
+            .ok_or_else(no_root)?,
+        _ => return Err(no_root()),
+    };
+    if exponent % 2 == 0 && root.numerator() != 0 {
+        return Err(format!(
+            "{} has two rational roots of degree {}: {} and -{}",
+            value, exponent, root, root
+        ));
+    }
+    Ok(root)
+}
+
+fn integer_root(value: u128, exponent: u32) -> Option<u128> {
+    let (mut low, mut high) = (0u128, value);
+    while low < high {
+        let middle = low + (high - low).div_ceil(2);
+        match middle.checked_pow(exponent) {
+            Some(power) if power <= value => low = middle,
+            _ => high = middle - 1,
+        }
+    }
+    (low.checked_pow(exponent) == Some(value)).then_some(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_job, parse_jobs, parse_query_args, rational_root, to_dot, Goal, Operation, Query,
+        YellJob, YellJobExpressions,
+    };
+    use aoc2022::rational::Rational;
+
+    fn example_lines() -> Vec<String> {
+        "root: pppw + sjmn
+        dbpl: 5
+        cczh: sllz + lgvd
+        zczc: 2
+        ptdq: humn - dvpt
+        dvpt: 3
+        lfqf: 4
+        humn: 5
+        ljgn: 2
+        sjmn: drzm * dbpl
+        sllz: 4
+        pppw: cczh / lfqf
+        lgvd: ljgn * ptdq
+        drzm: hmdt - zczc
+        hmdt: 32"
+            .lines()
+            .map(|l| l.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn example_from_description() {
+        let lines = example_lines();
+        let expressions =
+            YellJobExpressions::new(parse_jobs(&lines).unwrap(), &Query::default()).unwrap();
+
+        assert_eq!(Ok(152), expressions.target_value_as_in_first_part());
+        assert_eq!(Ok(301), expressions.solve_for_unknown());
+    }
+
+    #[test]
+    fn test_parse_job() {
+        assert_eq!(Ok(("dbpl", YellJob::Constant(5))), parse_job("dbpl: 5"));
+        assert_eq!(
+            Ok((
+                "root",
+                YellJob::BinaryExpression {
+                    operation: Operation::Plus,
+                    left: Box::new(YellJob::Monkey("pppw")),
+                    right: Box::new(YellJob::Monkey("sjmn")),
+                }
+            )),
+            parse_job("root: pppw + sjmn")
+        );
+        assert_eq!(
+            Ok((
+                "abc",
+                YellJob::BinaryExpression {
+                    operation: Operation::Minus,
+                    left: Box::new(YellJob::Negation(Box::new(YellJob::BinaryExpression {
+                        operation: Operation::Power,
+                        left: Box::new(YellJob::Constant(2)),
+                        right: Box::new(YellJob::Constant(3)),
+                    }))),
+                    right: Box::new(YellJob::BinaryExpression {
+                        operation: Operation::Modulo,
+                        left: Box::new(YellJob::BinaryExpression {
+                            operation: Operation::Plus,
+                            left: Box::new(YellJob::Monkey("x")),
+                            right: Box::new(YellJob::Constant(1)),
+                        }),
+                        right: Box::new(YellJob::Monkey("y")),
+                    }),
+                }
+            )),
+            parse_job("abc: -2 ^ 3 - (x + 1) % y")
+        );
+        assert!(parse_job("abc: (x + 1").is_err());
+        assert!(parse_job("abc: x + ").is_err());
+        assert!(parse_job("abc: x y").is_err());
+        assert!(parse_job("abc: x & y").is_err());
+    }
+
+    #[test]
+    fn test_extended_expressions() {
+        let lines = [
+            "root: a + b",
+            "a: (humn - 1) ^ 3 + -c",
+            "b: 10 % 4 * 100 + 17",
+            "c: 2 ^ 3 ^ 2",
+            "humn: 5",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect::<Vec<_>>();
+        let expressions =
+            YellJobExpressions::new(parse_jobs(&lines).unwrap(), &Query::default()).unwrap();
+
+        assert_eq!(
+            Ok(64 - 512 + 217),
+            expressions.target_value_as_in_first_part()
+        );
+        assert_eq!(Ok(10), expressions.solve_for_unknown());
+    }
+
+    #[test]
+    fn test_modulo_overflow() {
+        assert_eq!(
+            Some(format!("{} Modulo -1 overflows", i128::MIN)),
+            expressions_for(&[
+                "root: a + b",
+                "a: (0 - 2) ^ 127 % (0 - 1)",
+                "b: 1",
+                "humn: 1",
+            ])
+            .err()
+        );
+    }
+
+    #[test]
+    fn test_cycles_and_undefined_monkeys() {
+        let lines = ["root: a + b", "a: b * 2", "b: c - 1", "c: a + 1", "humn: 1"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        let error = YellJobExpressions::new(parse_jobs(&lines).unwrap(), &Query::default())
+            .err()
+            .unwrap();
+        assert_eq!("Monkeys form a cycle: a -> b -> c -> a", error);
+
+        let lines = ["root: a + humn", "a: zzz * 2", "humn: 1"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        let error = YellJobExpressions::new(parse_jobs(&lines).unwrap(), &Query::default())
+            .err()
+            .unwrap();
+        assert_eq!("Couldn't find a job for monkey zzz, referenced by a", error);
+    }
+
+    fn expressions_for(lines: &[&str]) -> Result<YellJobExpressions, String> {
+        let lines = lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        YellJobExpressions::new(parse_jobs(&lines)?, &Query::default())
+    }
+
+    #[test]
+    fn test_rational_root() {
+        let rational = Rational::integer;
+        assert_eq!(
+            Ok(Rational::new(-2, 3).unwrap()),
+            rational_root(Rational::new(-8, 27).unwrap(), rational(3))
+        );
+        let big = (1i128 << 42) + 1;
+        assert_eq!(
+            Ok(rational(big)),
+            rational_root(rational(big.pow(3)), rational(3))
+        );
+        assert_eq!(
+            Err(format!(
+                "{} has no rational root of degree 3",
+                big.pow(3) - 1
+            )),
+            rational_root(rational(big.pow(3) - 1), rational(3))
+        );
+        assert_eq!(Ok(rational(0)), rational_root(rational(0), rational(2)));
+        assert_eq!(
+            Err("9 has two rational roots of degree 2: 3 and -3".to_string()),
+            rational_root(rational(9), rational(2))
+        );
+        assert_eq!(
+            Err("-9 has no rational root of degree 2".to_string()),
+            rational_root(rational(-9), rational(2))
+        );
+    }
+
+    #[test]
+    fn test_exact_rational_solving() {
+        let expressions =
+            expressions_for(&["root: a + b", "a: humn / 4 * 8", "b: 6", "humn: 4"]).unwrap();
+        assert_eq!(Ok(14), expressions.target_value_as_in_first_part());
+        assert_eq!(Ok(3), expressions.solve_for_unknown());
+
+        let expressions = expressions_for(&[
+            "root: a + b",
+            "a: humn * 2 + humn",
+            "b: humn + 10",
+            "humn: 1",
+        ])
+        .unwrap();
+        assert_eq!(Ok(14), expressions.target_value_as_in_first_part());
+        assert_eq!(Ok(5), expressions.solve_for_unknown());
+
+        let expressions =
+            expressions_for(&["root: a + b", "a: humn * 2", "b: 5", "humn: 1"]).unwrap();
+        assert_eq!(
+            Err("Solution humn = 5/2 is not an integer".to_string()),
+            expressions.solve_for_unknown()
+        );
+
+        let expressions =
+            expressions_for(&["root: a + b", "a: humn * 2", "b: humn * 2", "humn: 1"]).unwrap();
+        assert_eq!(
+            Err("Monkey root operands have no unique solution for humn".to_string()),
+            expressions.solve_for_unknown()
+        );
+
+        assert!(expressions_for(&["root: a + b", "a: humn * humn", "b: 5", "humn: 1"]).is_err());
+    }
+
+    #[test]
+    fn test_arbitrary_unknown_and_target() {
+        let lines = example_lines();
+        let query = |unknown: &str, target: &str, goal: Goal| Query {
+            unknown: unknown.to_string(),
+            target: target.to_string(),
+            goal,
+        };
+
+        let expressions = YellJobExpressions::new(
+            parse_jobs(&lines).unwrap(),
+            &query("hmdt", "root", Goal::OperandsEqual),
+        )
+        .unwrap();
+        assert_eq!(Ok(152), expressions.target_value_as_in_first_part());
+        assert_eq!(
+            Err("Solution hmdt = 12/5 is not an integer".to_string()),
+            expressions.solve_for_unknown()
+        );
+
+        let expressions = YellJobExpressions::new(
+            parse_jobs(&lines).unwrap(),
+            &query("hmdt", "root", Goal::Value(152)),
+        )
+        .unwrap();
+        assert_eq!(Ok(32), expressions.solve_for_unknown());
+
+        let expressions = YellJobExpressions::new(
+            parse_jobs(&lines).unwrap(),
+            &query("ptdq", "pppw", Goal::Value(10)),
+        )
+        .unwrap();
+        assert_eq!(Ok(2), expressions.target_value_as_in_first_part());
+        assert_eq!(Ok(18), expressions.solve_for_unknown());
+
+        let expressions = YellJobExpressions::new(
+            parse_jobs(&lines).unwrap(),
+            &query("humn", "sjmn", Goal::Value(10)),
+        )
+        .unwrap();
+        assert_eq!(
+            Err("Monkey sjmn doesn't depend on monkey humn".to_string()),
+            expressions.solve_for_unknown()
+        );
+
+        assert!(YellJobExpressions::new(
+            parse_jobs(&lines).unwrap(),
+            &query("nobody", "root", Goal::OperandsEqual)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_query_args() {
+        let args = |args: &[&str]| {
+            parse_query_args(
+                &["day21"]
+                    .iter()
+                    .chain(args)
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(Ok(Query::default()), args(&[]));
+        assert_eq!(
+            Ok(Query {
+                unknown: "abc".to_string(),
+                target: "xyz".to_string(),
+                goal: Goal::Value(-5),
+            }),
+            args(&["--unknown", "abc", "--target", "xyz", "--equals", "-5"])
+        );
+        assert!(args(&["--equals", "five"]).is_err());
+        assert!(args(&["--target"]).is_err());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let lines = ["root: a + b", "a: -(humn - 1) * 2", "b: 7", "humn: 4"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        let jobs = parse_jobs(&lines).unwrap();
+
+        assert_eq!(
+            "digraph monkeys {
+    node [shape=box];
+    \"a\" [label=\"a\\n-(humn - 1) * 2\\n= -6\", color=red, penwidth=2];
+    \"b\" [label=\"b\\n= 7\"];
+    \"humn\" [label=\"humn\\n= 4\", color=red, penwidth=2];
+    \"root\" [label=\"root\\na + b\\n= 1\", color=red, penwidth=2];
+    \"humn\" -> \"a\" [color=red, penwidth=2];
+    \"a\" -> \"root\" [color=red, penwidth=2];
+    \"b\" -> \"root\";
+}
+",
+            to_dot(&jobs, &Query::default()).unwrap()
+        );
+
+        let lines = ["root: node + edge", "node: 3", "edge: humn * 2", "humn: 1"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        let dot = to_dot(&parse_jobs(&lines).unwrap(), &Query::default()).unwrap();
+        assert!(dot.contains("\n    \"node\" [label=\"node\\n= 3\"];\n"));
+        assert!(dot.contains("\n    \"node\" -> \"root\";\n"));
+        assert!(dot.contains("\n    \"humn\" -> \"edge\" [color=red, penwidth=2];\n"));
+    }
 }
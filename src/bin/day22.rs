@@ -1,4 +1,7 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env, fs,
+};
 
 use aoc2022::input::read_lines;
 use DieSide::*;
@@ -16,11 +19,59 @@ fn main() {
         }
     };
 
-    println!("Part 1: {:?}", map.final_password(WrapAroundMethod::In2d));
-    println!(
-        "Part 2: {:?}",
-        map.final_password(WrapAroundMethod::In3dCube)
-    );
+    println!("Part 1: {:?}", map.final_password(&In2d));
+    println!("Part 2: {:?}", map.final_password(&In3dCube));
+
+    let args = env::args().collect::<Vec<_>>();
+    let wrap_around_methods: [(&str, &dyn WrapAround); 4] = [
+        ("2d", &In2d),
+        ("cube", &In3dCube),
+        ("klein", &KleinBottle),
+        ("mobius", &MobiusStrip),
+    ];
+    if let Some(name) = args
+        .iter()
+        .position(|a| a == "--wrap")
+        .and_then(|i| args.get(i + 1))
+    {
+        match wrap_around_methods.iter().find(|(n, _)| n == name) {
+            Some((_, wrap_around)) => {
+                println!("Password ({}): {}", name, map.final_password(*wrap_around))
+            }
+            None => println!("Unknown wrap around method: {}", name),
+        }
+    }
+    if let Some(edges) = args
+        .iter()
+        .position(|a| a == "--edges")
+        .and_then(|i| args.get(i + 1))
+    {
+        match parse_edge_pairs(edges) {
+            Ok(edge_pairs) => match map.check_edge_pairs(&edge_pairs) {
+                Ok(()) => println!("Password (edges): {}", map.final_password(&edge_pairs)),
+                Err(err) => println!("Edge pairs don't fit the map: {}", err),
+            },
+            Err(err) => println!("Can't parse edge pairs: {}", err),
+        }
+    }
+    for (flag, wrap_around_method) in [
+        ("--trace-2d", wrap_around_methods[0].1),
+        ("--trace-cube", wrap_around_methods[1].1),
+    ] {
+        if let Some(path) = args
+            .iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+        {
+            match fs::write(
+                path,
+                map.render_path(&map.traced_path(wrap_around_method)) + "\n",
+            ) {
+                Ok(()) => println!("Path written to {}", path),
+                Err(err) => println!("Can't write path to {}: {}", path, err),
+            }
+        }
+    }
 }
 
 fn parse_map(lines: Vec<String>) -> Result<Map, String> {
@@ -39,15 +90,15 @@ fn parse_map(lines: Vec<String>) -> Result<Map, String> {
         return Err("Map width is 0".to_string());
     }
 
-    let square_size = ((map_lines
-        .iter()
-        .map(|l| l.iter().filter(|c| **c != ' ').count())
-        .sum::<usize>()
-        / 6) as f64)
-        .sqrt() as usize;
+    let square_size = face_size(
+        map_lines
+            .iter()
+            .map(|l| l.iter().filter(|c| **c != ' ').count())
+            .sum(),
+    )?;
 
-    let width_in_squares = map_width / square_size;
-    let height_in_squares = map_lines.len() / square_size;
+    let width_in_squares = map_width.div_ceil(square_size);
+    let height_in_squares = map_lines.len().div_ceil(square_size);
     let mut squares = vec![vec![None; width_in_squares]; height_in_squares];
 
     for square_y in 0..height_in_squares {
@@ -66,6 +117,18 @@ fn parse_map(lines: Vec<String>) -> Result<Map, String> {
     Map::new(squares, parse_instructions(&lines[lines.len() - 1])?)
 }
 
+fn face_size(tiles_count: usize) -> Result<usize, String> {
+    let face_area = tiles_count / 6;
+    let face_size = (face_area as f64).sqrt().round() as usize;
+    if face_size == 0 || !tiles_count.is_multiple_of(6) || face_size * face_size != face_area {
+        return Err(format!(
+            "Map has {} tiles, which can't be split into 6 square faces",
+            tiles_count
+        ));
+    }
+    Ok(face_size)
+}
+
 fn parse_square(
     map_lines: &Vec<Vec<char>>,
     square_coordinates: Coordinates,
@@ -80,14 +143,17 @@ fn parse_square(
         y: square_size * square_coordinates.y + square_size - 1,
     };
     let mut tile_rows = vec![];
+    let mut missing_tiles = 0;
     for tile_y in top_left_tile.y..=down_right_tile.y {
         let mut row = vec![];
         for tile_x in top_left_tile.x..=down_right_tile.x {
-            let tile = match map_lines[tile_y].get(tile_x) {
+            let tile = match map_lines.get(tile_y).and_then(|l| l.get(tile_x)) {
                 Some('.') => Tile::Open,
                 Some('#') => Tile::Wall,
-                Some(' ') => return Ok(None),
-                None => return Ok(None),
+                Some(' ') | None => {
+                    missing_tiles += 1;
+                    continue;
+                }
                 invalid_char @ _ => {
                     return Err(format!("Invalid character in map: {:?}", invalid_char))
                 }
@@ -96,40 +162,80 @@ fn parse_square(
         }
         tile_rows.push(row);
     }
-    Ok(Some(Square { tile_rows }))
+    if missing_tiles == square_size * square_size {
+        Ok(None)
+    } else if missing_tiles > 0 {
+        Err(format!(
+            "Face at square {:?} is missing {} of its {} tiles",
+            square_coordinates,
+            missing_tiles,
+            square_size * square_size
+        ))
+    } else {
+        Ok(Some(Square { tile_rows }))
+    }
+}
+
+fn parse_edge_pairs(line: &str) -> Result<EdgePairs, String> {
+    let parse_edge = |edge: &str| -> Result<SquareEdge, String> {
+        let parts = edge.split(',').collect::<Vec<_>>();
+        if parts.len() != 3 {
+            return Err(format!("Expected edge as x,y,direction, got '{}'", edge));
+        }
+        let coordinate = |part: &str| {
+            part.parse()
+                .map_err(|_| format!("Invalid square coordinate in edge '{}'", edge))
+        };
+        let direction = match parts[2] {
+            "U" => Up,
+            "R" => Right,
+            "D" => Down,
+            "L" => Left,
+            _ => return Err(format!("Invalid direction in edge '{}'", edge)),
+        };
+        Ok(SquareEdge {
+            coordinates: Coordinates {
+                x: coordinate(parts[0])?,
+                y: coordinate(parts[1])?,
+            },
+            direction,
+        })
+    };
+    let pairs = line
+        .split_whitespace()
+        .map(|pair| match pair.split_once('-') {
+            Some((from, to)) => Ok((parse_edge(from)?, parse_edge(to)?)),
+            None => Err(format!("Expected edge pair as edge-edge, got '{}'", pair)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    EdgePairs::new(&pairs)
 }
 
 fn parse_instructions(line: &str) -> Result<Vec<Instruction>, String> {
     let mut instructions = vec![];
-    let mut number_start = None;
-    for (i, char) in line.char_indices() {
-        if char.is_numeric() {
-            if number_start.is_none() {
-                number_start = Some(i);
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, char)) = chars.next() {
+        if char.is_ascii_digit() {
+            let mut end = start + 1;
+            while let Some((i, _)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                end = i + 1;
             }
+            instructions.push(Instruction::Go {
+                steps: line[start..end]
+                    .parse()
+                    .map_err(|e| format!("Can't parse go count: {}", e))?,
+                pause_on_wall: chars.next_if(|(_, c)| *c == '*').is_some(),
+            });
         } else {
-            if let Some(number_start) = number_start {
-                instructions.push(Instruction::Go(
-                    line[number_start..i]
-                        .parse()
-                        .map_err(|e| format!("Can't parse go count: {}", e))?,
-                ));
-            }
-            number_start = None;
-            match char {
-                'R' => instructions.push(Instruction::TurnClockwise),
-                'L' => instructions.push(Instruction::TurnCounterClockwise),
+            instructions.push(match char {
+                'R' => Instruction::TurnClockwise,
+                'L' => Instruction::TurnCounterClockwise,
+                'U' => Instruction::TurnAround,
+                'T' => Instruction::TeleportToStart,
                 _ => return Err(format!("Unexpected char in instructions line: {}", char)),
-            }
+            });
         }
     }
-    if let Some(number_start) = number_start {
-        instructions.push(Instruction::Go(
-            line[number_start..line.len()]
-                .parse()
-                .map_err(|e| format!("Can't parse go count: {}", e))?,
-        ));
-    }
     Ok(instructions)
 }
 
@@ -160,6 +266,17 @@ impl Map {
             }
         }
         if let Some(starting_square_coordinates) = Self::starting_square_coordinates(&squares) {
+            let faces = squares
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(_, square)| square.is_some())
+                        .map(move |(x, _)| Coordinates { x, y })
+                })
+                .collect::<Vec<_>>();
+            check_cube_net(&faces)?;
             let adjacent_edges =
                 Self::calculate_adjacent_edges(&squares, starting_square_coordinates)?;
             let square_size = squares[starting_square_coordinates.y][starting_square_coordinates.x]
@@ -194,6 +311,8 @@ impl Map {
         let mut from_square_to_die: HashMap<SquareEdge, DieEdge> = HashMap::new();
         let mut from_die_to_square: HashMap<DieEdge, SquareEdge> = HashMap::new();
         let mut assigned_squares: HashSet<Coordinates> = HashSet::new();
+        let mut square_per_die_side: HashMap<DieSide, Coordinates> = HashMap::new();
+        square_per_die_side.insert(One, starting_square_coordinates);
 
         Self::assign(
             SquareEdge {
@@ -226,6 +345,16 @@ impl Map {
                     let adjacent_die_edge = *DIE_EDGES
                         .get(from_square_to_die.get(&square_edge).unwrap())
                         .unwrap();
+                    if let Some(other_coordinates) = square_per_die_side
+                        .insert(adjacent_die_edge.side, adjacent_square_edge.coordinates)
+                    {
+                        return Err(format!(
+                            "Faces at squares {:?} and {:?} both fold onto die side {:?}",
+                            other_coordinates,
+                            adjacent_square_edge.coordinates,
+                            adjacent_die_edge.side
+                        ));
+                    }
                     Self::assign(
                         adjacent_square_edge,
                         adjacent_die_edge,
@@ -237,6 +366,27 @@ impl Map {
                 }
             }
         }
+        for (y, row) in squares.iter().enumerate() {
+            for (x, square) in row.iter().enumerate() {
+                let coordinates = Coordinates { x, y };
+                if square.is_some() && !assigned_squares.contains(&coordinates) {
+                    return Err(format!(
+                        "Face at square {:?} isn't connected to the rest of the net",
+                        coordinates
+                    ));
+                }
+            }
+        }
+        let missing_die_sides = [One, Two, Three, Four, Five, Six]
+            .into_iter()
+            .filter(|side| !square_per_die_side.contains_key(side))
+            .collect::<Vec<_>>();
+        if !missing_die_sides.is_empty() {
+            return Err(format!(
+                "No face folds onto die sides {:?}",
+                missing_die_sides
+            ));
+        }
         let mut result = HashMap::new();
         for y in 0..squares.len() {
             for x in 0..squares[0].len() {
@@ -308,33 +458,46 @@ impl Map {
             })
     }
 
-    fn final_password(&self, wrap_around_method: WrapAroundMethod) -> usize {
-        let final_position = self.position_after_following_instructions(wrap_around_method);
+    fn final_password(&self, wrap_around: &dyn WrapAround) -> usize {
+        let final_position = self.position_after_following_instructions(wrap_around, None);
         self.final_password_for_position(&final_position)
     }
 
-    fn position_after_following_instructions(
-        &self,
-        wrap_around_method: WrapAroundMethod,
-    ) -> MapPosition {
-        let mut current_position = MapPosition {
+    fn traced_path(&self, wrap_around: &dyn WrapAround) -> Vec<MapPosition> {
+        let mut path = vec![];
+        self.position_after_following_instructions(wrap_around, Some(&mut path));
+        path
+    }
+
+    fn starting_position(&self) -> MapPosition {
+        MapPosition {
             square_coordinates: Self::starting_square_coordinates(&self.squares).unwrap(),
             tile_coordinates: Coordinates { x: 0, y: 0 },
             direction: Right,
-        };
+        }
+    }
+
+    fn position_after_following_instructions(
+        &self,
+        wrap_around: &dyn WrapAround,
+        mut path: Option<&mut Vec<MapPosition>>,
+    ) -> MapPosition {
+        let mut current_position = self.starting_position();
+        if let Some(path) = path.as_mut() {
+            path.push(current_position);
+        }
+        let mut paused_steps = 0;
 
         for instruction in self.instructions.iter() {
             match instruction {
-                Instruction::Go(count) => {
-                    for _ in 0..*count {
-                        let next_position = self.shift(&current_position, wrap_around_method);
-                        match self.tile_at(next_position) {
-                            Tile::Open => {
-                                current_position = next_position;
-                            }
-                            Tile::Wall => break,
-                        }
-                    }
+                Instruction::Go {
+                    steps,
+                    pause_on_wall,
+                } => {
+                    let remaining_steps =
+                        self.go(&mut current_position, *steps, wrap_around, &mut path);
+                    paused_steps = if *pause_on_wall { remaining_steps } else { 0 };
+                    continue;
                 }
                 Instruction::TurnClockwise => {
                     current_position.direction = current_position.direction.turn_clockwise();
@@ -343,19 +506,99 @@ impl Map {
                     current_position.direction =
                         current_position.direction.turn_counter_clockwise();
                 }
+                Instruction::TurnAround => {
+                    current_position.direction = current_position.direction.opposite();
+                }
+                Instruction::TeleportToStart => {
+                    current_position = MapPosition {
+                        direction: current_position.direction,
+                        ..self.starting_position()
+                    };
+                    paused_steps = 0;
+                }
+            }
+            if let Some(path) = path.as_mut() {
+                path.push(current_position);
+            }
+            if paused_steps > 0 {
+                paused_steps = self.go(&mut current_position, paused_steps, wrap_around, &mut path);
             }
         }
 
         current_position
     }
 
+    fn go(
+        &self,
+        current_position: &mut MapPosition,
+        steps: usize,
+        wrap_around: &dyn WrapAround,
+        path: &mut Option<&mut Vec<MapPosition>>,
+    ) -> usize {
+        for step in 0..steps {
+            match self
+                .shift(current_position, wrap_around)
+                .map(|next_position| (next_position, self.tile_at(next_position)))
+            {
+                Some((next_position, Tile::Open)) => {
+                    *current_position = next_position;
+                }
+                _ => return steps - step,
+            }
+            if let Some(path) = path.as_mut() {
+                path.push(*current_position);
+            }
+        }
+        0
+    }
+
+    fn render_path(&self, path: &[MapPosition]) -> String {
+        let mut board = vec![
+            vec![' '; self.squares_width() * self.square_size];
+            self.squares_height() * self.square_size
+        ];
+        for (square_y, square_row) in self.squares.iter().enumerate() {
+            for (square_x, square) in square_row.iter().enumerate() {
+                if let Some(square) = square {
+                    for (tile_y, tile_row) in square.tile_rows.iter().enumerate() {
+                        for (tile_x, tile) in tile_row.iter().enumerate() {
+                            board[square_y * self.square_size + tile_y]
+                                [square_x * self.square_size + tile_x] = match tile {
+                                Tile::Open => '.',
+                                Tile::Wall => '#',
+                            };
+                        }
+                    }
+                }
+            }
+        }
+        for position in path {
+            let (row, column) = self.global_row_and_column(position);
+            board[row][column] = match position.direction {
+                Up => '^',
+                Right => '>',
+                Down => 'v',
+                Left => '<',
+            };
+        }
+        board
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn global_row_and_column(&self, position: &MapPosition) -> (usize, usize) {
+        (
+            position.square_coordinates.y * self.square_size + position.tile_coordinates.y,
+            position.square_coordinates.x * self.square_size + position.tile_coordinates.x,
+        )
+    }
+
     fn final_password_for_position(&self, position: &MapPosition) -> usize {
-        let global_row =
-            position.square_coordinates.y * self.square_size + position.tile_coordinates.y + 1;
-        let global_column =
-            position.square_coordinates.x * self.square_size + position.tile_coordinates.x + 1;
-        1000 * global_row
-            + 4 * global_column
+        let (row, column) = self.global_row_and_column(position);
+        1000 * (row + 1)
+            + 4 * (column + 1)
             + match position.direction {
                 Direction::Up => 3,
                 Direction::Right => 0,
@@ -372,23 +615,63 @@ impl Map {
         square.tile_rows[map_coordinates.tile_coordinates.y][map_coordinates.tile_coordinates.x]
     }
 
-    fn shift(&self, position: &MapPosition, wrap_around_method: WrapAroundMethod) -> MapPosition {
+    fn shift(&self, position: &MapPosition, wrap_around: &dyn WrapAround) -> Option<MapPosition> {
         if let Some(next_tile_coordinates_inside_same_square) =
             position
                 .tile_coordinates
                 .shift(position.direction, self.square_size, self.square_size)
         {
-            MapPosition {
+            Some(MapPosition {
                 square_coordinates: position.square_coordinates,
                 tile_coordinates: next_tile_coordinates_inside_same_square,
                 direction: position.direction,
-            }
+            })
         } else {
-            match wrap_around_method {
-                WrapAroundMethod::In2d => self.wrap_around_in_2d(position),
-                WrapAroundMethod::In3dCube => self.wrap_around_in_3d_cube(position),
-            }
+            wrap_around.wrap_around(self, position)
+        }
+    }
+
+    fn adjacent_in_2d(&self, position: &MapPosition) -> Option<MapPosition> {
+        let adjacent_square_coordinates = position.square_coordinates.shift(
+            position.direction,
+            self.squares_width(),
+            self.squares_height(),
+        )?;
+        if !self.is_square_present(adjacent_square_coordinates) {
+            return None;
         }
+        let mut position = self.wrap_around_in_2d(position);
+        position.square_coordinates = adjacent_square_coordinates;
+        Some(position)
+    }
+
+    fn first_tile_from_edge(&self, direction: Direction, line: usize) -> Option<MapPosition> {
+        let global_tile = |index: usize| match direction {
+            Up | Down => (index, line),
+            Right | Left => (line, index),
+        };
+        let length = match direction {
+            Up | Down => self.squares_height() * self.square_size,
+            Right | Left => self.squares_width() * self.square_size,
+        };
+        let indices: Box<dyn Iterator<Item = usize>> = match direction {
+            Down | Right => Box::new(0..length),
+            Up | Left => Box::new((0..length).rev()),
+        };
+        indices
+            .map(global_tile)
+            .map(|(row, column)| MapPosition {
+                square_coordinates: Coordinates {
+                    x: column / self.square_size,
+                    y: row / self.square_size,
+                },
+                tile_coordinates: Coordinates {
+                    x: column % self.square_size,
+                    y: row % self.square_size,
+                },
+                direction,
+            })
+            .find(|position| self.is_square_present(position.square_coordinates))
     }
 
     fn wrap_around_in_2d(&self, position: &MapPosition) -> MapPosition {
@@ -434,11 +717,11 @@ impl Map {
         }
     }
 
-    fn wrap_around_in_3d_cube(&self, position: &MapPosition) -> MapPosition {
-        let square_edge = SquareEdge {
-            coordinates: position.square_coordinates,
-            direction: position.direction,
-        };
+    fn wrap_through_edge(
+        &self,
+        position: &MapPosition,
+        adjacent_square_edge: &SquareEdge,
+    ) -> MapPosition {
         let index_on_adjacent_edge = self.square_size
             - match position.direction {
                 Up => position.tile_coordinates.x,
@@ -447,7 +730,6 @@ impl Map {
                 Left => self.square_size - position.tile_coordinates.y - 1,
             }
             - 1;
-        let adjacent_square_edge = self.adjacent_edges.get(&square_edge).unwrap();
         let tile_coordinates = match adjacent_square_edge.direction {
             Up => Coordinates {
                 x: index_on_adjacent_edge,
@@ -473,6 +755,20 @@ impl Map {
         }
     }
 
+    fn check_edge_pairs(&self, edge_pairs: &EdgePairs) -> Result<(), String> {
+        for edge in edge_pairs.adjacent_edges.keys() {
+            let present = self
+                .squares
+                .get(edge.coordinates.y)
+                .and_then(|row| row.get(edge.coordinates.x))
+                .is_some_and(|square| square.is_some());
+            if !present {
+                return Err(format!("Edge {:?} doesn't belong to a face", edge));
+            }
+        }
+        Ok(())
+    }
+
     fn squares_width(&self) -> usize {
         self.squares[0].len()
     }
@@ -497,14 +793,16 @@ enum Tile {
     Wall,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum Instruction {
-    Go(usize),
+    Go { steps: usize, pause_on_wall: bool },
     TurnClockwise,
     TurnCounterClockwise,
+    TurnAround,
+    TeleportToStart,
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 struct Coordinates {
     x: usize,
     y: usize,
@@ -581,7 +879,7 @@ impl Coordinates {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Direction {
     Up,
     Right,
@@ -635,13 +933,13 @@ fn directions_starting_from(d: Direction) -> [Direction; 4] {
     result
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 struct SquareEdge {
     coordinates: Coordinates,
     direction: Direction,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 enum DieSide {
     One,
     Two,
@@ -785,26 +1083,267 @@ lazy_static! {
     .collect();
 }
 
-#[derive(Clone, Copy)]
+lazy_static! {
+    static ref CUBE_NETS: Vec<Vec<Coordinates>> = [
+        ["#...", "####", "#..."],
+        ["#...", "####", ".#.."],
+        ["#...", "####", "..#."],
+        ["#...", "####", "...#"],
+        [".#..", "####", ".#.."],
+        [".#..", "####", "..#."],
+        ["##..", ".###", ".#.."],
+        ["##..", ".###", "..#."],
+        ["##..", ".###", "...#"],
+        ["##..", ".##.", "..##"],
+        ["###..", "..###", "....."],
+    ]
+    .iter()
+    .map(|rows| {
+        rows.iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| Coordinates { x, y })
+            })
+            .collect()
+    })
+    .collect();
+}
+
+fn normalized_shape(faces: &[(isize, isize)]) -> Vec<(isize, isize)> {
+    let min_x = faces.iter().map(|(x, _)| *x).min().unwrap_or(0);
+    let min_y = faces.iter().map(|(_, y)| *y).min().unwrap_or(0);
+    let mut shape = faces
+        .iter()
+        .map(|(x, y)| (x - min_x, y - min_y))
+        .collect::<Vec<_>>();
+    shape.sort();
+    shape
+}
+
+fn shape_orientations(faces: &[Coordinates]) -> Vec<Vec<(isize, isize)>> {
+    let mut orientation = faces
+        .iter()
+        .map(|c| (c.x as isize, c.y as isize))
+        .collect::<Vec<_>>();
+    let mut orientations = vec![];
+    for _ in 0..4 {
+        orientation = orientation.iter().map(|(x, y)| (-y, *x)).collect();
+        orientations.push(normalized_shape(&orientation));
+        orientations.push(normalized_shape(
+            &orientation
+                .iter()
+                .map(|(x, y)| (-x, *y))
+                .collect::<Vec<_>>(),
+        ));
+    }
+    orientations
+}
+
+fn check_cube_net(faces: &[Coordinates]) -> Result<(), String> {
+    let orientations = shape_orientations(faces);
+    if faces.len() == 6
+        && CUBE_NETS.iter().any(|net| {
+            let net_shape = normalized_shape(
+                &net.iter()
+                    .map(|c| (c.x as isize, c.y as isize))
+                    .collect::<Vec<_>>(),
+            );
+            orientations.contains(&net_shape)
+        })
+    {
+        return Ok(());
+    }
+
+    // Roll a cube over the faces and record which of its sides lands on each face.
+    // The vectors are the sides facing down, east and south on the current face, numbered
+    // like the die sides that calculate_adjacent_edges folds the first face onto.
+    type Side = (i8, i8, i8);
+    let neg = |(x, y, z): Side| (-x, -y, -z);
+    let die_side = |side: Side| match side {
+        (0, 0, -1) => One,
+        (0, 1, 0) => Two,
+        (-1, 0, 0) => Three,
+        (1, 0, 0) => Four,
+        (0, -1, 0) => Five,
+        _ => Six,
+    };
+    let mut rolled: HashMap<Coordinates, (Side, Side, Side)> = HashMap::new();
+    let mut face_per_side: HashMap<DieSide, Coordinates> = HashMap::new();
+    rolled.insert(faces[0], ((0, 0, -1), (1, 0, 0), (0, 1, 0)));
+    face_per_side.insert(One, faces[0]);
+    let mut queue = VecDeque::from([faces[0]]);
+    while let Some(coordinates) = queue.pop_front() {
+        let (down, east, south) = rolled[&coordinates];
+        let neighbours = [
+            (coordinates.x + 1, coordinates.y, (east, neg(down), south)),
+            (
+                coordinates.x.wrapping_sub(1),
+                coordinates.y,
+                (neg(east), down, south),
+            ),
+            (coordinates.x, coordinates.y + 1, (south, east, neg(down))),
+            (
+                coordinates.x,
+                coordinates.y.wrapping_sub(1),
+                (neg(south), east, down),
+            ),
+        ];
+        for (x, y, sides) in neighbours {
+            let neighbour = Coordinates { x, y };
+            if !faces.contains(&neighbour) || rolled.contains_key(&neighbour) {
+                continue;
+            }
+            if let Some(other) = face_per_side.insert(die_side(sides.0), neighbour) {
+                return Err(format!(
+                    "Faces at squares {:?} and {:?} both fold onto die side {:?}",
+                    other,
+                    neighbour,
+                    die_side(sides.0)
+                ));
+            }
+            rolled.insert(neighbour, sides);
+            queue.push_back(neighbour);
+        }
+    }
+    if let Some(face) = faces.iter().find(|face| !rolled.contains_key(face)) {
+        return Err(format!(
+            "Face at square {:?} isn't connected to the rest of the net",
+            face
+        ));
+    }
+    let missing_die_sides = [One, Two, Three, Four, Five, Six]
+        .into_iter()
+        .filter(|side| !face_per_side.contains_key(side))
+        .collect::<Vec<_>>();
+    if !missing_die_sides.is_empty() {
+        return Err(format!(
+            "No face folds onto die sides {:?}",
+            missing_die_sides
+        ));
+    }
+    Err("Faces don't form one of the 11 cube nets".to_string())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct MapPosition {
     square_coordinates: Coordinates,
     tile_coordinates: Coordinates,
     direction: Direction,
 }
 
-#[derive(Clone, Copy)]
-enum WrapAroundMethod {
-    In2d,
-    In3dCube,
+trait WrapAround {
+    fn wrap_around(&self, map: &Map, position: &MapPosition) -> Option<MapPosition>;
+}
+
+struct In2d;
+
+impl WrapAround for In2d {
+    fn wrap_around(&self, map: &Map, position: &MapPosition) -> Option<MapPosition> {
+        Some(map.wrap_around_in_2d(position))
+    }
+}
+
+struct In3dCube;
+
+impl WrapAround for In3dCube {
+    fn wrap_around(&self, map: &Map, position: &MapPosition) -> Option<MapPosition> {
+        let square_edge = SquareEdge {
+            coordinates: position.square_coordinates,
+            direction: position.direction,
+        };
+        Some(map.wrap_through_edge(position, map.adjacent_edges.get(&square_edge)?))
+    }
+}
+
+struct KleinBottle;
+
+impl WrapAround for KleinBottle {
+    fn wrap_around(&self, map: &Map, position: &MapPosition) -> Option<MapPosition> {
+        if let Some(adjacent) = map.adjacent_in_2d(position) {
+            return Some(adjacent);
+        }
+        match position.direction {
+            Right | Left => Some(map.wrap_around_in_2d(position)),
+            Up | Down => {
+                let (_, column) = map.global_row_and_column(position);
+                map.first_tile_from_edge(
+                    position.direction,
+                    map.squares_width() * map.square_size - column - 1,
+                )
+            }
+        }
+    }
+}
+
+struct MobiusStrip;
+
+impl WrapAround for MobiusStrip {
+    fn wrap_around(&self, map: &Map, position: &MapPosition) -> Option<MapPosition> {
+        if let Some(adjacent) = map.adjacent_in_2d(position) {
+            return Some(adjacent);
+        }
+        match position.direction {
+            Right | Left => {
+                let (row, _) = map.global_row_and_column(position);
+                map.first_tile_from_edge(
+                    position.direction,
+                    map.squares_height() * map.square_size - row - 1,
+                )
+            }
+            Up | Down => None,
+        }
+    }
+}
+
+struct EdgePairs {
+    adjacent_edges: HashMap<SquareEdge, SquareEdge>,
+}
+
+impl EdgePairs {
+    fn new(pairs: &[(SquareEdge, SquareEdge)]) -> Result<EdgePairs, String> {
+        let mut adjacent_edges = HashMap::new();
+        for (edge, other_edge) in pairs {
+            for (from, to) in [(edge, other_edge), (other_edge, edge)] {
+                if adjacent_edges.insert(*from, *to).is_some() {
+                    return Err(format!("Edge {:?} is paired more than once", from));
+                }
+                if edge == other_edge {
+                    break;
+                }
+            }
+        }
+        Ok(EdgePairs { adjacent_edges })
+    }
+}
+
+impl WrapAround for EdgePairs {
+    fn wrap_around(&self, map: &Map, position: &MapPosition) -> Option<MapPosition> {
+        let square_edge = SquareEdge {
+            coordinates: position.square_coordinates,
+            direction: position.direction,
+        };
+        match self.adjacent_edges.get(&square_edge) {
+            Some(adjacent_square_edge) => {
+                Some(map.wrap_through_edge(position, adjacent_square_edge))
+            }
+            None => map.adjacent_in_2d(position),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parse_map, WrapAroundMethod};
+    use crate::{
+        check_cube_net, face_size, parse_edge_pairs, parse_instructions, parse_map,
+        shape_orientations, Coordinates, Direction, EdgePairs, In2d, In3dCube, Instruction,
+        KleinBottle, MapPosition, MobiusStrip, WrapAround, CUBE_NETS,
+    };
 
-    #[test]
-    fn example_from_description() {
-        let lines = "        ...#
+    fn example_lines() -> Vec<String> {
+        "        ...#
         .#..
         #...
         ....
@@ -820,10 +1359,281 @@ mod tests {
 10R5L5R10L4R5L5"
             .lines()
             .map(|l| l.to_string())
-            .collect::<Vec<_>>();
+            .collect()
+    }
+
+    fn lines_for_shape(shape: &[(isize, isize)], face_size: usize) -> Vec<String> {
+        let height = shape.iter().map(|(_, y)| *y).max().unwrap() as usize + 1;
+        let mut lines = vec![String::new(); height * face_size];
+        for (y, line) in lines.iter_mut().enumerate() {
+            let row = (y / face_size) as isize;
+            let width = shape
+                .iter()
+                .filter(|(_, face_y)| *face_y == row)
+                .map(|(x, _)| *x)
+                .max()
+                .unwrap() as usize
+                + 1;
+            for x in 0..width {
+                for _ in 0..face_size {
+                    line.push(if shape.contains(&(x as isize, row)) {
+                        '.'
+                    } else {
+                        ' '
+                    });
+                }
+            }
+        }
+        lines.push(String::new());
+        lines.push("1".to_string());
+        lines
+    }
+
+    fn shape(rows: &[&str]) -> Vec<(isize, isize)> {
+        rows.iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| (x as isize, y as isize))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn example_from_description() {
+        let map = parse_map(example_lines()).unwrap();
+
+        assert_eq!(6032, map.final_password(&In2d));
+        assert_eq!(5031, map.final_password(&In3dCube));
+    }
+
+    #[test]
+    fn test_all_cube_nets_fold() {
+        assert_eq!(11, CUBE_NETS.len());
+        for net in CUBE_NETS.iter() {
+            for orientation in shape_orientations(net) {
+                let map = parse_map(lines_for_shape(&orientation, 2)).unwrap();
+                assert_eq!(24, map.adjacent_edges.len());
+                for (edge, adjacent_edge) in map.adjacent_edges.iter() {
+                    assert_eq!(Some(edge), map.adjacent_edges.get(adjacent_edge));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_cube_nets() {
+        assert_eq!(
+            "Faces at squares Coordinates { x: 0, y: 1 } and Coordinates { x: 1, y: 1 } both fold onto die side Two",
+            parse_map(lines_for_shape(&shape(&["###", "###"]), 2))
+                .err()
+                .unwrap()
+        );
+        assert_eq!(
+            "Face at square Coordinates { x: 5, y: 0 } isn't connected to the rest of the net",
+            parse_map(lines_for_shape(&shape(&["#....#", "####"]), 2))
+                .err()
+                .unwrap()
+        );
+        assert_eq!(
+            "Map has 20 tiles, which can't be split into 6 square faces",
+            parse_map(lines_for_shape(&shape(&["#", "####"]), 2))
+                .err()
+                .unwrap()
+        );
+        let faces = |rows: &[&str]| {
+            shape(rows)
+                .into_iter()
+                .map(|(x, y)| Coordinates {
+                    x: x as usize,
+                    y: y as usize,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            Err("No face folds onto die sides [Six]".to_string()),
+            check_cube_net(&faces(&["#", "####"]))
+        );
+        assert_eq!(
+            Err("No face folds onto die sides [Three, Six]".to_string()),
+            check_cube_net(&faces(&["#", "###"]))
+        );
+        assert_eq!(
+            Err("Faces at squares Coordinates { x: 0, y: 1 } and Coordinates { x: 4, y: 1 } both fold onto die side Two".to_string()),
+            check_cube_net(&faces(&["#", "#####"]))
+        );
+
+        let mut lines = lines_for_shape(&shape(&["#", "####", "#"]), 2);
+        lines[0] = ".".to_string();
+        lines[2].push('.');
+        assert_eq!(
+            "Face at square Coordinates { x: 0, y: 0 } is missing 1 of its 4 tiles",
+            parse_map(lines).err().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_face_size() {
+        assert_eq!(Ok(2), face_size(24));
+        assert_eq!(Ok(50), face_size(15000));
+        assert!(face_size(25).is_err());
+        assert!(face_size(12).is_err());
+        assert!(face_size(0).is_err());
+    }
+
+    #[test]
+    fn test_render_path() {
+        let map = parse_map(example_lines()).unwrap();
+
+        assert_eq!(
+            "        >>v#
+        .#v.
+        #.v.
+        ..v.
+...#...v..v#
+>>>v...>#.>>
+..#v...#....
+...>>>>v..#.
+        ...#....
+        .....#..
+        .#......
+        ......#.",
+            map.render_path(&map.traced_path(&In2d))
+        );
+
+        let path = map.traced_path(&In3dCube);
+        assert_eq!(
+            map.position_after_following_instructions(&In3dCube, None),
+            *path.last().unwrap()
+        );
+        assert_eq!(
+            "        >>v#
+        .#v.
+        #.v.
+        ..v.
+...#..^...v#
+.>>>>>^.#.>>
+.^#....#....
+.^........#.
+        ...#..v.
+        .....#v.
+        .#v<<<<.
+        ..v...#.",
+            map.render_path(&path)
+        );
+    }
+
+    fn example_with_instructions(instructions: &str) -> Vec<String> {
+        let mut lines = example_lines();
+        *lines.last_mut().unwrap() = instructions.to_string();
+        lines
+    }
+
+    #[test]
+    fn test_wrap_around_into_gap() {
+        let mut lines = example_lines();
+        lines[0].push_str(&" ".repeat(8));
+        for _ in 0..4 {
+            lines.insert(12, " ".repeat(4));
+        }
         let map = parse_map(lines).unwrap();
+        assert_eq!((5, 4), (map.squares_width(), map.squares_height()));
+
+        let down_from_bottom_left = MapPosition {
+            square_coordinates: Coordinates { x: 0, y: 1 },
+            tile_coordinates: Coordinates { x: 0, y: 3 },
+            direction: Direction::Down,
+        };
+        assert_eq!(None, KleinBottle.wrap_around(&map, &down_from_bottom_left));
+        let left_from_top = MapPosition {
+            square_coordinates: Coordinates { x: 2, y: 0 },
+            tile_coordinates: Coordinates { x: 0, y: 0 },
+            direction: Direction::Left,
+        };
+        assert_eq!(None, MobiusStrip.wrap_around(&map, &left_from_top));
+        assert_eq!(5044, map.final_password(&KleinBottle));
+        assert_eq!(12056, map.final_password(&MobiusStrip));
+    }
+
+    #[test]
+    fn test_wrap_around_topologies() {
+        let map = parse_map(example_with_instructions("L1")).unwrap();
+        assert_eq!(12039, map.final_password(&In2d));
+        assert_eq!(8035, map.final_password(&KleinBottle));
+        assert_eq!(1039, map.final_password(&MobiusStrip));
+
+        let map = parse_map(example_with_instructions("LL1")).unwrap();
+        assert_eq!(1038, map.final_password(&In2d));
+        assert_eq!(12066, map.final_password(&MobiusStrip));
+
+        let map = parse_map(example_lines()).unwrap();
+        let cube_edges = map
+            .adjacent_edges
+            .iter()
+            .map(|(edge, adjacent_edge)| (*edge, *adjacent_edge))
+            .filter(|(edge, adjacent_edge)| edge < adjacent_edge)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            5031,
+            map.final_password(&EdgePairs::new(&cube_edges).unwrap())
+        );
+    }
 
-        assert_eq!(6032, map.final_password(WrapAroundMethod::In2d));
-        assert_eq!(5031, map.final_password(WrapAroundMethod::In3dCube));
+    #[test]
+    fn test_parse_edge_pairs() {
+        let map = parse_map(example_lines()).unwrap();
+        let edge_pairs = parse_edge_pairs("2,0,U-0,1,U 2,0,L-1,1,U").unwrap();
+        assert_eq!(4, edge_pairs.adjacent_edges.len());
+        assert_eq!(Ok(()), map.check_edge_pairs(&edge_pairs));
+        assert!(map
+            .check_edge_pairs(&parse_edge_pairs("0,0,U-0,1,U").unwrap())
+            .is_err());
+        assert!(parse_edge_pairs("2,0,U-0,1,U 2,0,U-1,1,U").is_err());
+        assert!(parse_edge_pairs("2,0,X-0,1,U").is_err());
+        assert!(parse_edge_pairs("2,0,U").is_err());
+    }
+
+    #[test]
+    fn test_parse_instructions() {
+        assert_eq!(
+            Ok(vec![
+                Instruction::Go {
+                    steps: 10,
+                    pause_on_wall: false
+                },
+                Instruction::TurnClockwise,
+                Instruction::Go {
+                    steps: 5,
+                    pause_on_wall: true
+                },
+                Instruction::TurnAround,
+                Instruction::TurnCounterClockwise,
+                Instruction::TeleportToStart,
+                Instruction::Go {
+                    steps: 2,
+                    pause_on_wall: false
+                },
+            ]),
+            parse_instructions("10R5*ULT2")
+        );
+        assert!(parse_instructions("10X").is_err());
+        assert!(parse_instructions("*").is_err());
+    }
+
+    #[test]
+    fn test_extended_instructions() {
+        let password = |instructions: &str| {
+            parse_map(example_with_instructions(instructions))
+                .unwrap()
+                .final_password(&In2d)
+        };
+        assert_eq!(1038, password("U"));
+        assert_eq!(1036, password("2T"));
+        assert_eq!(2036, password("2RT3L"));
+        assert_eq!(1045, password("10R"));
+        assert_eq!(7045, password("10*R"));
+        assert_eq!(1045, password("10*0R"));
     }
 }
@@ -1,27 +1,144 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::HashSet,
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
 
 use aoc2022::input::read_lines;
 
 use Direction::*;
 
-#[macro_use]
-extern crate lazy_static;
-
 fn main() {
+    let mut args = env::args().collect::<Vec<_>>();
+    let mut frame_args = vec![];
+    for flag in ["--frames", "--format", "--max-rounds"] {
+        if let Some(index) = args.iter().position(|a| a == flag) {
+            frame_args.extend(args.drain(index..(index + 2).min(args.len())));
+        }
+    }
+    let rules = match parse_rules_args(&args) {
+        Ok(rules) => rules,
+        Err(err) => {
+            println!("Invalid arguments: {}", err);
+            return;
+        }
+    };
     let mut elves = parse_initial_coordinates(
         read_lines("inputs/day23.txt")
             .map(|l| l.chars().collect())
             .collect(),
     )
-    .unwrap();
+    .unwrap()
+    .with_rules(rules);
+    let initial_coordinates = elves.board.coordinates();
 
     elves.simulate_rounds(10);
     println!(
         "Part 1: {:?}",
         elves.empty_ground_tiles_count_in_smallest_rectangle()
     );
-    elves.simulate_rounds_until_nobody_moves();
-    println!("Part 2: {:?}", elves.rounds);
+    if !elves.rules.stay_if_alone {
+        println!("Part 2: elves that move when alone never stop");
+    } else if elves.simulate_rounds_until_nobody_moves(MAX_ROUNDS) {
+        println!("Part 2: {:?}", elves.rounds);
+    } else {
+        println!("Part 2: elves still move after {} rounds", elves.rounds);
+    }
+
+    let arg_value = |flag: &str| {
+        frame_args
+            .iter()
+            .position(|a| a == flag)
+            .and_then(|i| frame_args.get(i + 1))
+    };
+    if let Some(directory) = arg_value("--frames") {
+        let format = match arg_value("--format").map(|f| f.as_str()) {
+            None | Some("ascii") => FrameFormat::Ascii,
+            Some("pbm") => FrameFormat::Pbm,
+            Some(format) => {
+                println!("Unknown frame format: {}", format);
+                return;
+            }
+        };
+        let max_rounds = match arg_value("--max-rounds").map(|m| m.parse()) {
+            None => 1000,
+            Some(Ok(max_rounds)) => max_rounds,
+            Some(Err(err)) => {
+                println!("Invalid max rounds: {}", err);
+                return;
+            }
+        };
+        let mut elves = Elves::new(initial_coordinates).with_rules(elves.rules.clone());
+        let snapshots = elves.snapshots().take(max_rounds + 1).collect::<Vec<_>>();
+        match export_frames(Path::new(directory), &snapshots, format) {
+            Ok(()) => println!("{} frames written to {}", snapshots.len(), directory),
+            Err(err) => println!("Can't write frames: {}", err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rules {
+    directions: Vec<Direction>,
+    rotate_directions: bool,
+    stay_if_alone: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            directions: vec![North, South, West, East],
+            rotate_directions: true,
+            stay_if_alone: true,
+        }
+    }
+}
+
+impl Rules {
+    fn directions_for_round(&self, round: usize) -> Vec<Direction> {
+        let mut directions = self.directions.clone();
+        if self.rotate_directions && !directions.is_empty() {
+            directions.rotate_left(round % self.directions.len());
+        }
+        directions
+    }
+}
+
+fn parse_rules_args(args: &[String]) -> Result<Rules, String> {
+    let mut rules = Rules::default();
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-rotation" => rules.rotate_directions = false,
+            "--move-alone" => rules.stay_if_alone = false,
+            "--eight-directions" => {
+                rules
+                    .directions
+                    .extend([NorthWest, NorthEast, SouthWest, SouthEast]);
+            }
+            "--directions" => {
+                let value = args.next().ok_or(format!("Missing value for '{}'", arg))?;
+                rules.directions = value
+                    .split(',')
+                    .map(|d| match d.trim() {
+                        "N" => Ok(North),
+                        "S" => Ok(South),
+                        "W" => Ok(West),
+                        "E" => Ok(East),
+                        "NW" => Ok(NorthWest),
+                        "NE" => Ok(NorthEast),
+                        "SW" => Ok(SouthWest),
+                        "SE" => Ok(SouthEast),
+                        _ => Err(format!("Unknown direction '{}'", d)),
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+    }
+    Ok(rules)
 }
 
 fn parse_initial_coordinates(lines: Vec<Vec<char>>) -> Result<Elves, String> {
@@ -56,207 +173,635 @@ struct Coordinates {
     y: isize,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     North,
     South,
     West,
     East,
+    NorthWest,
+    NorthEast,
+    SouthWest,
+    SouthEast,
 }
 
-lazy_static! {
-    static ref DIRECTIONS: [Direction; 4] = [North, South, West, East];
+impl Direction {
+    fn offset(self) -> (isize, isize) {
+        match self {
+            North => (0, -1),
+            South => (0, 1),
+            West => (-1, 0),
+            East => (1, 0),
+            NorthWest => (-1, -1),
+            NorthEast => (1, -1),
+            SouthWest => (-1, 1),
+            SouthEast => (1, 1),
+        }
+    }
+
+    fn offsets_to_check(self) -> Vec<(isize, isize)> {
+        let (dx, dy) = self.offset();
+        NEIGHBOUR_OFFSETS
+            .iter()
+            .filter(|(x, y)| x * dx + y * dy > 0)
+            .cloned()
+            .collect()
+    }
 }
 
-impl Direction {
-    fn starting_from_index(index: usize) -> [Direction; 4] {
-        let mut result = DIRECTIONS.clone();
-        result.rotate_left(index);
-        result
+const NEIGHBOUR_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+const BOARD_PADDING: isize = 16;
+
+const MAX_ROUNDS: usize = 2000;
+
+#[derive(Debug, Clone)]
+struct Board {
+    origin: Coordinates,
+    words_per_row: usize,
+    rows: Vec<Vec<u64>>,
+    empty_row: Vec<u64>,
+}
+
+impl Board {
+    fn new(elves_coordinates: &HashSet<Coordinates>) -> Board {
+        let min_x = elves_coordinates.iter().map(|c| c.x).min().unwrap_or(0);
+        let max_x = elves_coordinates.iter().map(|c| c.x).max().unwrap_or(0);
+        let min_y = elves_coordinates.iter().map(|c| c.y).min().unwrap_or(0);
+        let max_y = elves_coordinates.iter().map(|c| c.y).max().unwrap_or(0);
+        let origin = Coordinates {
+            x: min_x - BOARD_PADDING,
+            y: min_y - BOARD_PADDING,
+        };
+        let words_per_row = ((max_x - origin.x + BOARD_PADDING) as usize) / 64 + 1;
+        let height = (max_y - origin.y + BOARD_PADDING) as usize + 1;
+        let mut board = Board {
+            origin,
+            words_per_row,
+            rows: vec![vec![0; words_per_row]; height],
+            empty_row: vec![0; words_per_row],
+        };
+        for coordinates in elves_coordinates {
+            let x = (coordinates.x - origin.x) as usize;
+            let y = (coordinates.y - origin.y) as usize;
+            board.rows[y][x / 64] |= 1 << (x % 64);
+        }
+        board
+    }
+
+    fn row(&self, y: isize) -> &[u64] {
+        if y < 0 {
+            return &self.empty_row;
+        }
+        self.rows.get(y as usize).unwrap_or(&self.empty_row)
+    }
+
+    fn coordinates(&self) -> HashSet<Coordinates> {
+        let mut coordinates = HashSet::new();
+        for (y, row) in self.rows.iter().enumerate() {
+            for (word_index, word) in row.iter().enumerate() {
+                let mut word = *word;
+                while word != 0 {
+                    let x = word_index * 64 + word.trailing_zeros() as usize;
+                    coordinates.insert(Coordinates {
+                        x: self.origin.x + x as isize,
+                        y: self.origin.y + y as isize,
+                    });
+                    word &= word - 1;
+                }
+            }
+        }
+        coordinates
+    }
+
+    fn len(&self) -> usize {
+        self.rows
+            .iter()
+            .flatten()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn columns(&self) -> Vec<u64> {
+        let mut columns = self.empty_row.clone();
+        for row in self.rows.iter() {
+            or_assign(&mut columns, row);
+        }
+        columns
+    }
+
+    fn touches_border(&self) -> bool {
+        let columns = self.columns();
+        is_not_empty(&self.rows[0])
+            || is_not_empty(&self.rows[self.rows.len() - 1])
+            || columns[0] & 1 != 0
+            || columns[self.words_per_row - 1] >> 63 != 0
+    }
+
+    fn smallest_rectangle(&self) -> Option<Rectangle> {
+        let first_row = self.rows.iter().position(|r| is_not_empty(r))?;
+        let last_row = self.rows.iter().rposition(|r| is_not_empty(r))?;
+        let columns = self.columns();
+        let first_word = columns.iter().position(|w| *w != 0)?;
+        let last_word = columns.iter().rposition(|w| *w != 0)?;
+        let first_column = first_word * 64 + columns[first_word].trailing_zeros() as usize;
+        let last_column = last_word * 64 + 63 - columns[last_word].leading_zeros() as usize;
+        Some(Rectangle {
+            top_left: Coordinates {
+                x: self.origin.x + first_column as isize,
+                y: self.origin.y + first_row as isize,
+            },
+            bottom_right: Coordinates {
+                x: self.origin.x + last_column as isize,
+                y: self.origin.y + last_row as isize,
+            },
+        })
     }
+
+    fn neighbours(&self, y: isize, (dx, dy): (isize, isize)) -> Vec<u64> {
+        shifted(self.row(y + dy), dx)
+    }
+}
+
+fn shifted(row: &[u64], dx: isize) -> Vec<u64> {
+    match dx {
+        0 => row.to_vec(),
+        1 => (0..row.len())
+            .map(|i| (row[i] >> 1) | row.get(i + 1).map_or(0, |w| w << 63))
+            .collect(),
+        -1 => (0..row.len())
+            .map(|i| (row[i] << 1) | if i > 0 { row[i - 1] >> 63 } else { 0 })
+            .collect(),
+        _ => panic!("Can't shift row by {}", dx),
+    }
+}
+
+fn or_assign(row: &mut [u64], other: &[u64]) {
+    row.iter_mut().zip(other).for_each(|(w, o)| *w |= o);
+}
+
+fn and_not_assign(row: &mut [u64], other: &[u64]) {
+    row.iter_mut().zip(other).for_each(|(w, o)| *w &= !o);
+}
+
+fn is_not_empty(row: &[u64]) -> bool {
+    row.iter().any(|w| *w != 0)
 }
 
 #[derive(Debug)]
 struct Elves {
     rounds: usize,
-    first_direction_to_propose_index: usize,
-    elves_coordinates: HashSet<Coordinates>,
+    rules: Rules,
+    board: Board,
 }
 
 impl Elves {
     fn new(elves_coordinates: HashSet<Coordinates>) -> Elves {
         Elves {
             rounds: 0,
-            elves_coordinates,
-            first_direction_to_propose_index: 0,
+            rules: Rules::default(),
+            board: Board::new(&elves_coordinates),
         }
     }
 
+    fn with_rules(self, rules: Rules) -> Elves {
+        Elves { rules, ..self }
+    }
+
     fn simulate_rounds(&mut self, rounds: usize) {
         for _ in 0..rounds {
             self.simulate_round();
         }
     }
 
-    fn simulate_rounds_until_nobody_moves(&mut self) {
-        loop {
+    fn simulate_rounds_until_nobody_moves(&mut self, max_rounds: usize) -> bool {
+        while self.rounds < max_rounds {
             if !self.simulate_round() {
-                return;
+                return true;
             }
         }
+        false
     }
 
     fn simulate_round(&mut self) -> bool {
-        let mut proposed_moves = HashMap::new();
-        for elf_coordinates in self.elves_coordinates.iter() {
-            if let Some(proposed_coordinates) = self.propose_move(
-                elf_coordinates,
-                &Direction::starting_from_index(self.first_direction_to_propose_index),
-            ) {
-                proposed_moves
-                    .entry(proposed_coordinates)
-                    .or_insert_with(|| vec![])
-                    .push(elf_coordinates.clone());
+        if self.board.touches_border() {
+            self.board = Board::new(&self.board.coordinates());
+        }
+        let directions = self.rules.directions_for_round(self.rounds);
+        let offsets_to_check = directions
+            .iter()
+            .map(|d| d.offsets_to_check())
+            .collect::<Vec<_>>();
+        let height = self.board.rows.len() as isize;
+
+        let mut proposals = vec![vec![]; directions.len()];
+        for y in 0..height {
+            let mut crowded = self.board.empty_row.clone();
+            for offset in NEIGHBOUR_OFFSETS {
+                or_assign(&mut crowded, &self.board.neighbours(y, offset));
+            }
+            let mut undecided = self.board.row(y).to_vec();
+            if self.rules.stay_if_alone {
+                undecided
+                    .iter_mut()
+                    .zip(&crowded)
+                    .for_each(|(w, c)| *w &= c);
+            }
+            for (direction_index, offsets) in offsets_to_check.iter().enumerate() {
+                let mut proposing = undecided.clone();
+                for offset in offsets {
+                    and_not_assign(&mut proposing, &self.board.neighbours(y, *offset));
+                }
+                and_not_assign(&mut undecided, &proposing);
+                proposals[direction_index].push(proposing);
             }
         }
+
+        let row_of = |rows: &Vec<Vec<u64>>, y: isize| -> Vec<u64> {
+            if y < 0 || y >= height {
+                self.board.empty_row.clone()
+            } else {
+                rows[y as usize].clone()
+            }
+        };
+        let mut rows = self.board.rows.clone();
         let mut any_elf_moved = false;
-        for (proposed_coordinates, from_coordinates) in proposed_moves.iter() {
-            if from_coordinates.len() == 1 {
+        for y in 0..height {
+            let arrivals = directions
+                .iter()
+                .enumerate()
+                .map(|(direction_index, direction)| {
+                    let (dx, dy) = direction.offset();
+                    shifted(&row_of(&proposals[direction_index], y - dy), -dx)
+                })
+                .collect::<Vec<_>>();
+            let mut proposed_once = self.board.empty_row.clone();
+            let mut proposed_more_than_once = self.board.empty_row.clone();
+            for arrival in arrivals.iter() {
+                for i in 0..self.board.words_per_row {
+                    proposed_more_than_once[i] |= proposed_once[i] & arrival[i];
+                    proposed_once[i] |= arrival[i];
+                }
+            }
+            for (direction, mut arrival) in directions.iter().zip(arrivals) {
+                and_not_assign(&mut arrival, &proposed_more_than_once);
+                if !is_not_empty(&arrival) {
+                    continue;
+                }
                 any_elf_moved = true;
-                self.elves_coordinates.remove(&from_coordinates[0]);
-                self.elves_coordinates.insert(proposed_coordinates.clone());
+                let (dx, dy) = direction.offset();
+                and_not_assign(&mut rows[(y - dy) as usize], &shifted(&arrival, dx));
+                or_assign(&mut rows[y as usize], &arrival);
             }
         }
-        self.first_direction_to_propose_index =
-            (self.first_direction_to_propose_index + 1) % DIRECTIONS.len();
+        self.board.rows = rows;
+
         self.rounds += 1;
         any_elf_moved
     }
 
-    fn propose_move(
-        &self,
-        elf_coordinates: &Coordinates,
-        directions_order: &[Direction; 4],
-    ) -> Option<Coordinates> {
-        if (-1..=1).all(|dy| -> bool {
-            (-1..=1).all(|dx| -> bool {
-                dx == 0 && dy == 0
-                    || !self.elves_coordinates.contains(&Coordinates {
-                        x: elf_coordinates.x + dx,
-                        y: elf_coordinates.y + dy,
-                    })
-            })
-        }) {
+    fn empty_ground_tiles_count_in_smallest_rectangle(&self) -> usize {
+        match self.board.smallest_rectangle() {
+            Some(rectangle) => rectangle.width() * rectangle.height() - self.board.len(),
+            None => 0,
+        }
+    }
+
+    fn snapshot(&self) -> RoundSnapshot {
+        RoundSnapshot {
+            round: self.rounds,
+            elves_coordinates: self.board.coordinates(),
+            smallest_rectangle: self.board.smallest_rectangle(),
+        }
+    }
+
+    fn snapshots(&mut self) -> RoundSnapshots<'_> {
+        RoundSnapshots {
+            elves: self,
+            started: false,
+            finished: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rectangle {
+    top_left: Coordinates,
+    bottom_right: Coordinates,
+}
+
+impl Rectangle {
+    fn width(&self) -> usize {
+        (self.bottom_right.x - self.top_left.x + 1) as usize
+    }
+
+    fn height(&self) -> usize {
+        (self.bottom_right.y - self.top_left.y + 1) as usize
+    }
+
+    fn enclosing(&self, other: &Rectangle) -> Rectangle {
+        Rectangle {
+            top_left: Coordinates {
+                x: self.top_left.x.min(other.top_left.x),
+                y: self.top_left.y.min(other.top_left.y),
+            },
+            bottom_right: Coordinates {
+                x: self.bottom_right.x.max(other.bottom_right.x),
+                y: self.bottom_right.y.max(other.bottom_right.y),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RoundSnapshot {
+    round: usize,
+    elves_coordinates: HashSet<Coordinates>,
+    smallest_rectangle: Option<Rectangle>,
+}
+
+struct RoundSnapshots<'a> {
+    elves: &'a mut Elves,
+    started: bool,
+    finished: bool,
+}
+
+impl Iterator for RoundSnapshots<'_> {
+    type Item = RoundSnapshot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
             return None;
         }
-        for direction in directions_order.iter() {
-            let (coordinates_to_check, proposed_coordinates) = match direction {
-                North => (
-                    [
-                        Coordinates {
-                            x: elf_coordinates.x - 1,
-                            y: elf_coordinates.y - 1,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x,
-                            y: elf_coordinates.y - 1,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x + 1,
-                            y: elf_coordinates.y - 1,
-                        },
-                    ],
-                    Coordinates {
-                        x: elf_coordinates.x,
-                        y: elf_coordinates.y - 1,
-                    },
-                ),
-                South => (
-                    [
-                        Coordinates {
-                            x: elf_coordinates.x - 1,
-                            y: elf_coordinates.y + 1,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x,
-                            y: elf_coordinates.y + 1,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x + 1,
-                            y: elf_coordinates.y + 1,
-                        },
-                    ],
-                    Coordinates {
-                        x: elf_coordinates.x,
-                        y: elf_coordinates.y + 1,
-                    },
-                ),
-                West => (
-                    [
-                        Coordinates {
-                            x: elf_coordinates.x - 1,
-                            y: elf_coordinates.y - 1,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x - 1,
-                            y: elf_coordinates.y,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x - 1,
-                            y: elf_coordinates.y + 1,
-                        },
-                    ],
-                    Coordinates {
-                        x: elf_coordinates.x - 1,
-                        y: elf_coordinates.y,
-                    },
-                ),
-                East => (
-                    [
-                        Coordinates {
-                            x: elf_coordinates.x + 1,
-                            y: elf_coordinates.y - 1,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x + 1,
-                            y: elf_coordinates.y,
-                        },
-                        Coordinates {
-                            x: elf_coordinates.x + 1,
-                            y: elf_coordinates.y + 1,
-                        },
-                    ],
-                    Coordinates {
-                        x: elf_coordinates.x + 1,
-                        y: elf_coordinates.y,
-                    },
-                ),
-            };
-            if coordinates_to_check
-                .iter()
-                .all(|c| !self.elves_coordinates.contains(c))
-            {
-                return Some(proposed_coordinates);
-            }
+        if self.started && !self.elves.simulate_round() {
+            self.finished = true;
+            return None;
         }
-        return None;
+        self.started = true;
+        Some(self.elves.snapshot())
     }
+}
 
-    fn empty_ground_tiles_count_in_smallest_rectangle(&self) -> usize {
-        let smallest_rectangle_width = (self.elves_coordinates.iter().map(|c| c.x).max().unwrap()
-            - self.elves_coordinates.iter().map(|c| c.x).min().unwrap()
-            + 1) as usize;
-        let smallest_rectangle_height = (self.elves_coordinates.iter().map(|c| c.y).max().unwrap()
-            - self.elves_coordinates.iter().map(|c| c.y).min().unwrap()
-            + 1) as usize;
+#[derive(Debug, Clone, Copy)]
+enum FrameFormat {
+    Ascii,
+    Pbm,
+}
+
+fn enclosing_rectangle(snapshots: &[RoundSnapshot]) -> Option<Rectangle> {
+    snapshots
+        .iter()
+        .filter_map(|s| s.smallest_rectangle)
+        .reduce(|a, b| a.enclosing(&b))
+}
+
+fn write_frame(
+    writer: &mut impl Write,
+    snapshot: &RoundSnapshot,
+    rectangle: &Rectangle,
+    format: FrameFormat,
+) -> io::Result<()> {
+    if let FrameFormat::Pbm = format {
+        writeln!(writer, "P1")?;
+        writeln!(writer, "# round {}", snapshot.round)?;
+        writeln!(writer, "{} {}", rectangle.width(), rectangle.height())?;
+    }
+    for y in rectangle.top_left.y..=rectangle.bottom_right.y {
+        let row = (rectangle.top_left.x..=rectangle.bottom_right.x)
+            .map(|x| snapshot.elves_coordinates.contains(&Coordinates { x, y }))
+            .map(|elf| match (format, elf) {
+                (FrameFormat::Ascii, true) => "#",
+                (FrameFormat::Ascii, false) => ".",
+                (FrameFormat::Pbm, true) => "1",
+                (FrameFormat::Pbm, false) => "0",
+            })
+            .collect::<Vec<_>>();
+        match format {
+            FrameFormat::Ascii => writeln!(writer, "{}", row.concat())?,
+            FrameFormat::Pbm => writeln!(writer, "{}", row.join(" "))?,
+        }
+    }
+    Ok(())
+}
 
-        smallest_rectangle_width * smallest_rectangle_height - self.elves_coordinates.len()
+fn export_frames(
+    directory: &Path,
+    snapshots: &[RoundSnapshot],
+    format: FrameFormat,
+) -> io::Result<()> {
+    let rectangle = match enclosing_rectangle(snapshots) {
+        Some(rectangle) => rectangle,
+        None => return Ok(()),
+    };
+    fs::create_dir_all(directory)?;
+    let extension = match format {
+        FrameFormat::Ascii => "txt",
+        FrameFormat::Pbm => "pbm",
+    };
+    for snapshot in snapshots {
+        let path = directory.join(format!("round_{:05}.{}", snapshot.round, extension));
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_frame(&mut writer, snapshot, &rectangle, format)?;
+        writer.flush()?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parse_initial_coordinates;
+    use std::collections::HashSet;
+
+    use aoc2022::random::XorShift;
+
+    use crate::{
+        enclosing_rectangle, parse_initial_coordinates, parse_rules_args, write_frame, Coordinates,
+        Direction::*, Elves, FrameFormat, Rules,
+    };
+
+    mod reference {
+        use std::collections::{HashMap, HashSet};
+
+        use crate::Coordinates;
+
+        use Direction::*;
+
+        #[derive(Debug, Clone, Copy)]
+        enum Direction {
+            North,
+            South,
+            West,
+            East,
+        }
+
+        const DIRECTIONS: [Direction; 4] = [North, South, West, East];
+
+        impl Direction {
+            fn starting_from_index(index: usize) -> [Direction; 4] {
+                let mut result = DIRECTIONS;
+                result.rotate_left(index);
+                result
+            }
+        }
+
+        #[derive(Debug)]
+        pub struct Elves {
+            first_direction_to_propose_index: usize,
+            pub elves_coordinates: HashSet<Coordinates>,
+        }
+
+        impl Elves {
+            pub fn new(elves_coordinates: HashSet<Coordinates>) -> Elves {
+                Elves {
+                    elves_coordinates,
+                    first_direction_to_propose_index: 0,
+                }
+            }
+
+            pub fn simulate_round(&mut self) -> bool {
+                let mut proposed_moves: HashMap<Coordinates, Vec<Coordinates>> = HashMap::new();
+                for elf_coordinates in self.elves_coordinates.iter() {
+                    if let Some(proposed_coordinates) = self.propose_move(
+                        elf_coordinates,
+                        &Direction::starting_from_index(self.first_direction_to_propose_index),
+                    ) {
+                        proposed_moves
+                            .entry(proposed_coordinates)
+                            .or_default()
+                            .push(*elf_coordinates);
+                    }
+                }
+                let mut any_elf_moved = false;
+                for (proposed_coordinates, from_coordinates) in proposed_moves.iter() {
+                    if from_coordinates.len() == 1 {
+                        any_elf_moved = true;
+                        self.elves_coordinates.remove(&from_coordinates[0]);
+                        self.elves_coordinates.insert(*proposed_coordinates);
+                    }
+                }
+                self.first_direction_to_propose_index =
+                    (self.first_direction_to_propose_index + 1) % DIRECTIONS.len();
+                any_elf_moved
+            }
+
+            fn propose_move(
+                &self,
+                elf_coordinates: &Coordinates,
+                directions_order: &[Direction; 4],
+            ) -> Option<Coordinates> {
+                if (-1..=1).all(|dy| -> bool {
+                    (-1..=1).all(|dx| -> bool {
+                        dx == 0 && dy == 0
+                            || !self.elves_coordinates.contains(&Coordinates {
+                                x: elf_coordinates.x + dx,
+                                y: elf_coordinates.y + dy,
+                            })
+                    })
+                }) {
+                    return None;
+                }
+                for direction in directions_order.iter() {
+                    let (coordinates_to_check, proposed_coordinates) = match direction {
+                        North => (
+                            [
+                                Coordinates {
+                                    x: elf_coordinates.x - 1,
+                                    y: elf_coordinates.y - 1,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x,
+                                    y: elf_coordinates.y - 1,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x + 1,
+                                    y: elf_coordinates.y - 1,
+                                },
+                            ],
+                            Coordinates {
+                                x: elf_coordinates.x,
+                                y: elf_coordinates.y - 1,
+                            },
+                        ),
+                        South => (
+                            [
+                                Coordinates {
+                                    x: elf_coordinates.x - 1,
+                                    y: elf_coordinates.y + 1,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x,
+                                    y: elf_coordinates.y + 1,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x + 1,
+                                    y: elf_coordinates.y + 1,
+                                },
+                            ],
+                            Coordinates {
+                                x: elf_coordinates.x,
+                                y: elf_coordinates.y + 1,
+                            },
+                        ),
+                        West => (
+                            [
+                                Coordinates {
+                                    x: elf_coordinates.x - 1,
+                                    y: elf_coordinates.y - 1,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x - 1,
+                                    y: elf_coordinates.y,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x - 1,
+                                    y: elf_coordinates.y + 1,
+                                },
+                            ],
+                            Coordinates {
+                                x: elf_coordinates.x - 1,
+                                y: elf_coordinates.y,
+                            },
+                        ),
+                        East => (
+                            [
+                                Coordinates {
+                                    x: elf_coordinates.x + 1,
+                                    y: elf_coordinates.y - 1,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x + 1,
+                                    y: elf_coordinates.y,
+                                },
+                                Coordinates {
+                                    x: elf_coordinates.x + 1,
+                                    y: elf_coordinates.y + 1,
+                                },
+                            ],
+                            Coordinates {
+                                x: elf_coordinates.x + 1,
+                                y: elf_coordinates.y,
+                            },
+                        ),
+                    };
+                    if coordinates_to_check
+                        .iter()
+                        .all(|c| !self.elves_coordinates.contains(c))
+                    {
+                        return Some(proposed_coordinates);
+                    }
+                }
+                None
+            }
+        }
+    }
 
     #[test]
     fn example_from_description() {
@@ -280,7 +825,142 @@ mod tests {
         .unwrap();
         elves.simulate_rounds(10);
         assert_eq!(110, elves.empty_ground_tiles_count_in_smallest_rectangle());
-        elves.simulate_rounds_until_nobody_moves();
+        assert!(elves.simulate_rounds_until_nobody_moves(1000));
         assert_eq!(20, elves.rounds);
     }
+
+    #[test]
+    fn test_bitboard_against_reference() {
+        let mut random = XorShift::new(23);
+        for _ in 0..20 {
+            let size = random.next_in_range(1..30);
+            let mut coordinates = HashSet::new();
+            for y in 0..size {
+                for x in 0..random.next_in_range(1..100) {
+                    if random.next_in_range(0..3) == 0 {
+                        coordinates.insert(Coordinates { x, y });
+                    }
+                }
+            }
+            let mut elves = Elves::new(coordinates.clone());
+            let mut reference = reference::Elves::new(coordinates);
+            for _ in 0..40 {
+                assert_eq!(reference.simulate_round(), elves.simulate_round());
+                assert_eq!(reference.elves_coordinates, elves.board.coordinates());
+            }
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let coordinates = |elves: &[(isize, isize)]| {
+            elves
+                .iter()
+                .map(|(x, y)| Coordinates { x: *x, y: *y })
+                .collect::<HashSet<_>>()
+        };
+
+        let mut elves = Elves::new(coordinates(&[(0, 0)]));
+        assert!(!elves.simulate_round());
+
+        let mut elves = Elves::new(coordinates(&[(0, 0)])).with_rules(Rules {
+            stay_if_alone: false,
+            ..Rules::default()
+        });
+        elves.simulate_rounds(3);
+        assert_eq!(coordinates(&[(-1, 0)]), elves.board.coordinates());
+
+        let mut elves = Elves::new(coordinates(&[(0, 0)])).with_rules(Rules {
+            stay_if_alone: false,
+            rotate_directions: false,
+            ..Rules::default()
+        });
+        elves.simulate_rounds(3);
+        assert_eq!(coordinates(&[(0, -3)]), elves.board.coordinates());
+
+        let mut elves = Elves::new(coordinates(&[(0, 0), (1, 0)])).with_rules(Rules {
+            directions: vec![NorthWest, NorthEast],
+            rotate_directions: false,
+            stay_if_alone: true,
+        });
+        assert!(elves.simulate_round());
+        assert_eq!(coordinates(&[(-1, -1), (2, -1)]), elves.board.coordinates());
+        assert!(!elves.simulate_round());
+    }
+
+    #[test]
+    fn test_parse_rules_args() {
+        let args = |args: &[&str]| {
+            parse_rules_args(
+                &["day23"]
+                    .iter()
+                    .chain(args)
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(Ok(Rules::default()), args(&[]));
+        assert_eq!(
+            Ok(Rules {
+                directions: vec![East, NorthWest],
+                rotate_directions: false,
+                stay_if_alone: false,
+            }),
+            args(&["--directions", "E,NW", "--no-rotation", "--move-alone"])
+        );
+        assert_eq!(
+            Ok(8),
+            args(&["--eight-directions"]).map(|rules| rules.directions.len())
+        );
+        assert!(args(&["--directions", "E,X"]).is_err());
+        assert!(args(&["--directions"]).is_err());
+    }
+
+    #[test]
+    fn test_snapshots_and_frames() {
+        let mut elves = parse_initial_coordinates(
+            ".....
+..##.
+..#..
+.....
+..##.
+....."
+                .lines()
+                .map(|l| l.chars().collect())
+                .collect(),
+        )
+        .unwrap();
+        let snapshots = elves.snapshots().collect::<Vec<_>>();
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            snapshots.iter().map(|s| s.round).collect::<Vec<_>>()
+        );
+        assert_eq!(4, elves.rounds);
+
+        let rectangle = enclosing_rectangle(&snapshots).unwrap();
+        let frames = snapshots
+            .iter()
+            .map(|snapshot| {
+                let mut frame = vec![];
+                write_frame(&mut frame, snapshot, &rectangle, FrameFormat::Ascii).unwrap();
+                String::from_utf8(frame).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ".....\n..##.\n..#..\n.....\n..##.\n.....\n",
+                "..##.\n.....\n..#..\n...#.\n..#..\n.....\n",
+                ".....\n..##.\n.#...\n....#\n.....\n..#..\n",
+                "..#..\n....#\n#....\n....#\n.....\n..#..\n",
+            ],
+            frames
+        );
+
+        let mut frame = vec![];
+        write_frame(&mut frame, &snapshots[0], &rectangle, FrameFormat::Pbm).unwrap();
+        assert_eq!(
+            "P1\n# round 0\n5 6\n0 0 0 0 0\n0 0 1 1 0\n0 0 1 0 0\n0 0 0 0 0\n0 0 1 1 0\n0 0 0 0 0\n",
+            String::from_utf8(frame).unwrap()
+        );
+    }
 }
@@ -1,17 +1,50 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
+    env,
+    hash::Hash,
     ops::{Index, IndexMut},
+    time::Instant,
 };
 
-use aoc2022::input::read_lines;
+use aoc2022::{input::read_lines, random::XorShift};
+
+const PRECOMPUTED_CELLS_LIMIT: usize = 1 << 24;
 
 fn main() {
-    let map = parse_map(
-        read_lines("inputs/day24.txt")
-            .map(|l| l.chars().collect())
-            .collect::<Vec<Vec<char>>>(),
-    )
-    .unwrap();
+    let lines = read_lines("inputs/day24.txt")
+        .map(|l| l.chars().collect())
+        .collect::<Vec<Vec<char>>>();
+    let args = env::args().collect::<Vec<_>>();
+
+    if args.iter().any(|a| a == "--bench") {
+        benchmark(&lines);
+        return;
+    }
+
+    let mut map = match parse_map(lines) {
+        Ok(map) => map,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    if let Some(occupancy) = args
+        .iter()
+        .position(|a| a == "--occupancy")
+        .and_then(|i| args.get(i + 1))
+    {
+        match occupancy.as_str() {
+            "precomputed" => map = map.with_occupancy(OccupancyKind::Precomputed),
+            "bitmasks" => map = map.with_occupancy(OccupancyKind::Bitmasks),
+            _ => {
+                println!(
+                    "Unknown occupancy '{}', expected precomputed or bitmasks",
+                    occupancy
+                );
+                return;
+            }
+        }
+    }
 
     println!(
         "Part 1: {:?}",
@@ -25,6 +58,171 @@ fn main() {
             map.goal_coordinates()
         ])
     );
+
+    match parse_trip_args(&args) {
+        Ok(Some(trip)) => match map.plan_trip(&trip) {
+            Ok(Some(route)) => println!(
+                "Trip: {} minutes visiting {}",
+                route[route.len() - 1].time,
+                map.visiting_order(&route, &trip.waypoints)
+                    .iter()
+                    .map(|c| format!("{},{}", c.x + 1, c.y))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            ),
+            Ok(None) => println!("No route found"),
+            Err(error) => println!("{}", error),
+        },
+        Ok(None) => {}
+        Err(error) => println!("{}", error),
+    }
+
+    if let Some(replay) = args
+        .iter()
+        .position(|a| a == "--replay")
+        .and_then(|i| args.get(i + 1))
+    {
+        let coordinates_to_visit = match replay.as_str() {
+            "single" => vec![map.goal_coordinates()],
+            "round-trip" => vec![
+                map.goal_coordinates(),
+                map.start_coordinates(),
+                map.goal_coordinates(),
+            ],
+            _ => {
+                println!("Unknown replay '{}', expected single or round-trip", replay);
+                return;
+            }
+        };
+        match map.shortest_route_visiting_all(coordinates_to_visit) {
+            Some(route) => print!("{}", map.render_route(&route)),
+            None => println!("No route found"),
+        }
+    }
+}
+
+fn benchmark(lines: &[Vec<char>]) {
+    let mut rng = XorShift::new(24);
+    let basins = [
+        ("input", lines.to_vec()),
+        ("random 127x61", random_basin(&mut rng, 127, 61, 0.1)),
+        ("random 211x89", random_basin(&mut rng, 211, 89, 0.1)),
+    ];
+    for (name, lines) in basins {
+        let map = match parse_map(lines) {
+            Ok(map) => map,
+            Err(error) => {
+                println!("{}: {}", name, error);
+                continue;
+            }
+        };
+        println!(
+            "{}: {}x{} basin, {} minutes cycle",
+            name,
+            map.width,
+            map.height - 2,
+            map.total_cycle_time
+        );
+        for kind in [OccupancyKind::Precomputed, OccupancyKind::Bitmasks] {
+            if kind == OccupancyKind::Precomputed
+                && map.total_cycle_time * map.width * map.height > PRECOMPUTED_CELLS_LIMIT * 16
+            {
+                println!("  {:?}: skipped, too large", kind);
+                continue;
+            }
+            let start = Instant::now();
+            let map = map.with_occupancy(kind);
+            let build_elapsed = start.elapsed();
+            let start = Instant::now();
+            let part1 = map.shortest_path_time_visiting_all(vec![map.goal_coordinates()]);
+            let part1_elapsed = start.elapsed();
+            println!(
+                "  {:?}: {} bytes, built in {:?}, part 1 {:?} in {:?}",
+                kind,
+                map.occupancy.memory_usage(),
+                build_elapsed,
+                part1,
+                part1_elapsed
+            );
+        }
+    }
+}
+
+fn random_basin(rng: &mut XorShift, width: usize, height: usize, density: f64) -> Vec<Vec<char>> {
+    let mut lines = vec![];
+    for y in 0..height + 2 {
+        let mut line = vec![];
+        for x in 0..width + 2 {
+            line.push(if y == 0 && x == 1 || y == height + 1 && x == width {
+                '.'
+            } else if y == 0 || y == height + 1 || x == 0 || x == width + 1 {
+                '#'
+            } else if (rng.next_u64() % 1000) as f64 >= density * 1000.0 {
+                '.'
+            } else {
+                ['>', '<', '^', 'v'][(rng.next_u64() % 4) as usize]
+            });
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[derive(Debug, PartialEq)]
+struct Trip {
+    waypoints: Vec<Coordinates>,
+    any_order: bool,
+    finish: Option<Coordinates>,
+}
+
+fn parse_trip_args(args: &[String]) -> Result<Option<Trip>, String> {
+    let value_of = |flag: &str| -> Result<Option<&String>, String> {
+        match args.iter().position(|a| a == flag) {
+            Some(i) => args
+                .get(i + 1)
+                .map(Some)
+                .ok_or(format!("Missing value for {}", flag)),
+            None => Ok(None),
+        }
+    };
+    let any_order = args.iter().any(|a| a == "--any-order");
+    let finish = value_of("--finish")?
+        .map(|f| parse_input_coordinates(f))
+        .transpose()?;
+    match value_of("--waypoints")? {
+        Some(waypoints) => Ok(Some(Trip {
+            waypoints: waypoints
+                .split_whitespace()
+                .map(parse_input_coordinates)
+                .collect::<Result<_, _>>()?,
+            any_order,
+            finish,
+        })),
+        None if any_order || finish.is_some() => {
+            Err("--any-order and --finish require --waypoints".to_string())
+        }
+        None => Ok(None),
+    }
+}
+
+fn parse_input_coordinates(str: &str) -> Result<Coordinates, String> {
+    let (column, row) = str.split_once(',').ok_or(format!(
+        "Invalid coordinates '{}', expected column,row",
+        str
+    ))?;
+    let column = column
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid column in '{}': {}", str, e))?;
+    let row = row
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid row in '{}': {}", str, e))?;
+    if column == 0 {
+        return Err(format!("Coordinates '{}' are in the side wall", str));
+    }
+    Ok(Coordinates {
+        x: column - 1,
+        y: row,
+    })
 }
 
 fn parse_map(lines: Vec<Vec<char>>) -> Result<Map, String> {
@@ -39,6 +237,27 @@ fn parse_map(lines: Vec<Vec<char>>) -> Result<Map, String> {
         return Err("Non square map".to_string());
     }
 
+    let mut gaps = vec![];
+    for row_index in [0, lines.len() - 1] {
+        for (column_index, value) in lines[row_index].iter().enumerate() {
+            if *value == '.' && column_index > 0 && column_index < width_with_borders - 1 {
+                gaps.push(Coordinates {
+                    x: column_index - 1,
+                    y: row_index,
+                });
+            }
+        }
+    }
+    if gaps.is_empty() {
+        return Err("No gaps in the basin walls".to_string());
+    }
+    if let Some(row_index) = lines
+        .iter()
+        .position(|l| l[0] != '#' || l[width_with_borders - 1] != '#')
+    {
+        return Err(format!("Gap in the side walls at row {}", row_index));
+    }
+
     let mut per_row = vec![vec![]; lines.len()];
     let mut per_column = vec![vec![]; width_with_borders - 2];
 
@@ -77,7 +296,7 @@ fn parse_map(lines: Vec<Vec<char>>) -> Result<Map, String> {
         }
     }
 
-    Ok(Map::new(per_row, per_column))
+    Ok(Map::new(per_row, per_column, gaps))
 }
 
 #[derive(Debug, Clone)]
@@ -88,10 +307,14 @@ struct Wind {
 
 impl Wind {
     fn would_be_at(&self, index: usize, time: usize, cycle_time: usize) -> bool {
+        self.position_at(time, cycle_time) == index
+    }
+
+    fn position_at(&self, time: usize, cycle_time: usize) -> usize {
         if self.forward {
-            (self.starting_index + time) % cycle_time == index
+            (self.starting_index + time) % cycle_time
         } else {
-            (self.starting_index + (cycle_time - (time % cycle_time))) % cycle_time == index
+            (self.starting_index + (cycle_time - (time % cycle_time))) % cycle_time
         }
     }
 }
@@ -127,15 +350,158 @@ impl IndexMut<(usize, usize)> for Vec2D {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OccupancyKind {
+    Precomputed,
+    Bitmasks,
+}
+
+impl OccupancyKind {
+    fn for_size(width: usize, height: usize, total_cycle_time: usize) -> OccupancyKind {
+        if total_cycle_time * width * height <= PRECOMPUTED_CELLS_LIMIT {
+            OccupancyKind::Precomputed
+        } else {
+            OccupancyKind::Bitmasks
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Occupancy {
+    Precomputed(Vec<Vec2D>),
+    Bitmasks(BlizzardBitmasks),
+}
+
+impl Occupancy {
+    fn memory_usage(&self) -> usize {
+        match self {
+            Occupancy::Precomputed(possible_moves_at_time) => possible_moves_at_time
+                .iter()
+                .map(|v| v.values.len() * std::mem::size_of::<bool>())
+                .sum(),
+            Occupancy::Bitmasks(bitmasks) => [
+                &bitmasks.right,
+                &bitmasks.left,
+                &bitmasks.down,
+                &bitmasks.up,
+            ]
+            .iter()
+            .flat_map(|masks| masks.iter())
+            .map(|mask| mask.len() * std::mem::size_of::<u64>())
+            .sum(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BlizzardBitmasks {
+    right: Vec<Vec<u64>>,
+    left: Vec<Vec<u64>>,
+    down: Vec<Vec<u64>>,
+    up: Vec<Vec<u64>>,
+}
+
+impl BlizzardBitmasks {
+    fn new(winds_per_row: &[Vec<Wind>], winds_per_column: &[Vec<Wind>]) -> BlizzardBitmasks {
+        let width = winds_per_column.len();
+        let inner_height = winds_per_row.len() - 2;
+        let masks = |winds: &[Vec<Wind>], forward: bool, len: usize| -> Vec<Vec<u64>> {
+            winds
+                .iter()
+                .map(|winds| {
+                    let mut mask = vec![0; len.div_ceil(64)];
+                    for wind in winds.iter().filter(|w| w.forward == forward) {
+                        mask[wind.starting_index / 64] |= 1 << (wind.starting_index % 64);
+                    }
+                    mask
+                })
+                .collect()
+        };
+        BlizzardBitmasks {
+            right: masks(winds_per_row, true, width),
+            left: masks(winds_per_row, false, width),
+            down: masks(winds_per_column, true, inner_height),
+            up: masks(winds_per_column, false, inner_height),
+        }
+    }
+
+    fn is_blizzard(&self, x: usize, y: usize, time: usize) -> bool {
+        let width = self.down.len();
+        let inner_height = self.right.len() - 2;
+        let (horizontal_shift, vertical_shift) = (time % width, time % inner_height);
+        let inner_y = y - 1;
+        is_bit_set(&self.right[y], (x + width - horizontal_shift) % width)
+            || is_bit_set(&self.left[y], (x + horizontal_shift) % width)
+            || is_bit_set(
+                &self.down[x],
+                (inner_y + inner_height - vertical_shift) % inner_height,
+            )
+            || is_bit_set(&self.up[x], (inner_y + vertical_shift) % inner_height)
+    }
+}
+
+fn is_bit_set(mask: &[u64], index: usize) -> bool {
+    mask[index / 64] & (1 << (index % 64)) != 0
+}
+
 #[derive(Debug)]
 struct Map {
-    possible_moves_at_time: Vec<Vec2D>,
+    occupancy: Occupancy,
+    total_cycle_time: usize,
+    winds_per_row: Vec<Vec<Wind>>,
+    winds_per_column: Vec<Vec<Wind>>,
+    gaps: Vec<Coordinates>,
     height: usize,
     width: usize,
 }
 
 impl Map {
-    fn new(winds_per_row: Vec<Vec<Wind>>, winds_per_column: Vec<Vec<Wind>>) -> Map {
+    fn new(
+        winds_per_row: Vec<Vec<Wind>>,
+        winds_per_column: Vec<Vec<Wind>>,
+        gaps: Vec<Coordinates>,
+    ) -> Map {
+        let width = winds_per_column.len();
+        let height = winds_per_row.len();
+        let total_cycle_time = least_common_multiple(width, height - 2);
+        let kind = OccupancyKind::for_size(width, height, total_cycle_time);
+        Map {
+            occupancy: Map::occupancy(&winds_per_row, &winds_per_column, &gaps, kind),
+            total_cycle_time,
+            winds_per_row,
+            winds_per_column,
+            gaps,
+            width,
+            height,
+        }
+    }
+
+    fn with_occupancy(&self, kind: OccupancyKind) -> Map {
+        Map {
+            occupancy: Map::occupancy(
+                &self.winds_per_row,
+                &self.winds_per_column,
+                &self.gaps,
+                kind,
+            ),
+            total_cycle_time: self.total_cycle_time,
+            winds_per_row: self.winds_per_row.clone(),
+            winds_per_column: self.winds_per_column.clone(),
+            gaps: self.gaps.clone(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    fn occupancy(
+        winds_per_row: &[Vec<Wind>],
+        winds_per_column: &[Vec<Wind>],
+        gaps: &[Coordinates],
+        kind: OccupancyKind,
+    ) -> Occupancy {
+        if kind == OccupancyKind::Bitmasks {
+            return Occupancy::Bitmasks(BlizzardBitmasks::new(winds_per_row, winds_per_column));
+        }
         let horizontal_cycle_time = winds_per_column.len();
         let vertical_cycle_time = winds_per_row.len() - 2;
         let total_cycle_time = least_common_multiple(horizontal_cycle_time, vertical_cycle_time);
@@ -144,7 +510,9 @@ impl Map {
         let possible_moves_at_time = (0..total_cycle_time)
             .map(|time| -> Vec2D {
                 let mut possible_moves = Vec2D::new(width, height);
-                possible_moves[(0, 0)] = true; // start, the rest of row are walls
+                for gap in gaps {
+                    possible_moves[(gap.x, gap.y)] = true; // the rest of top and bottom rows are walls
+                }
                 for y in 1..height - 1 {
                     for x in 0..width {
                         let blizzard_from_vertical_wind = winds_per_column[x]
@@ -157,60 +525,176 @@ impl Map {
                             !blizzard_from_vertical_wind && !blizzard_from_horizontal_wind;
                     }
                 }
-                possible_moves[(width - 1, height - 1)] = true; // finish, the rest of row are walls
                 possible_moves
             })
             .collect();
 
-        Map {
-            possible_moves_at_time,
-            width,
-            height,
-        }
-    }
-
-    fn total_cycle_time(&self) -> usize {
-        self.possible_moves_at_time.len()
+        Occupancy::Precomputed(possible_moves_at_time)
     }
 
     fn shortest_path_time_visiting_all(
         &self,
         coordinates_to_visit: Vec<Coordinates>,
     ) -> Option<usize> {
-        let mut start = CoordinatesInTime {
-            x: 0,
-            y: 0,
+        self.shortest_route_visiting_all(coordinates_to_visit)
+            .map(|route| route[route.len() - 1].time)
+    }
+
+    fn shortest_route_visiting_all(
+        &self,
+        coordinates_to_visit: Vec<Coordinates>,
+    ) -> Option<Vec<CoordinatesInTime>> {
+        self.shortest_route_through(&self.start_coordinates(), &coordinates_to_visit)
+    }
+
+    fn shortest_route_through(
+        &self,
+        start: &Coordinates,
+        waypoints: &[Coordinates],
+    ) -> Option<Vec<CoordinatesInTime>> {
+        let mut route = vec![CoordinatesInTime {
+            x: start.x,
+            y: start.y,
+            time: 0,
+        }];
+        for to_visit in waypoints.iter() {
+            let part = self.shortest_route(&route[route.len() - 1], to_visit)?;
+            route.extend(part.into_iter().skip(1));
+        }
+        Some(route)
+    }
+
+    fn shortest_route_in_any_order(
+        &self,
+        start: &Coordinates,
+        waypoints: &[Coordinates],
+        finish: Option<&Coordinates>,
+    ) -> Option<Vec<CoordinatesInTime>> {
+        let with_visited = |coordinates: &CoordinatesInTime, visited: u64| -> u64 {
+            waypoints
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| coordinates.same_as(w))
+                .fold(visited, |visited, (i, _)| visited | 1 << i)
+        };
+        let all_visited = u64::MAX >> (64 - waypoints.len().max(1));
+        let all_visited = if waypoints.is_empty() { 0 } else { all_visited };
+        let is_done = |coordinates: &CoordinatesInTime, visited: u64| -> bool {
+            visited == all_visited && finish.is_none_or(|f| coordinates.same_as(f))
+        };
+
+        let start = CoordinatesInTime {
+            x: start.x,
+            y: start.y,
             time: 0,
         };
-        let mut total_time = 0;
-        for to_visit in coordinates_to_visit.iter() {
-            if let Some(finish) = self.shortest_path_time(&start, to_visit) {
-                total_time += finish.time - start.time;
-                start = finish;
-            } else {
-                return None;
+        let start_visited = with_visited(&start, 0);
+        if is_done(&start, start_visited) {
+            return Some(vec![start]);
+        }
+        let mut queue = VecDeque::new();
+        let mut seen = HashSet::new();
+        let mut previous = HashMap::new();
+        seen.insert((self.normalize(&start), start_visited));
+        queue.push_back((start, start_visited));
+
+        while let Some((current_coordinates, visited)) = queue.pop_front() {
+            for possible_move in self.possible_moves(&current_coordinates) {
+                let move_visited = with_visited(&possible_move, visited);
+                if !seen.insert((self.normalize(&possible_move), move_visited)) {
+                    continue;
+                }
+                let state = (possible_move, move_visited);
+                previous.insert(state.clone(), (current_coordinates.clone(), visited));
+                if is_done(&state.0, state.1) {
+                    return Some(
+                        reconstruct_route(&previous, state)
+                            .into_iter()
+                            .map(|(coordinates, _)| coordinates)
+                            .collect(),
+                    );
+                }
+                queue.push_back(state);
             }
         }
-        Some(total_time)
+
+        None
     }
 
-    fn shortest_path_time(
+    fn plan_trip(&self, trip: &Trip) -> Result<Option<Vec<CoordinatesInTime>>, String> {
+        for waypoint in trip.waypoints.iter().chain(trip.finish.iter()) {
+            if !self.is_open(waypoint) {
+                return Err(format!(
+                    "Waypoint {},{} is not inside the basin",
+                    waypoint.x + 1,
+                    waypoint.y
+                ));
+            }
+        }
+        if trip.any_order {
+            if trip.waypoints.len() > 64 {
+                return Err("At most 64 waypoints can be visited in any order".to_string());
+            }
+            Ok(self.shortest_route_in_any_order(
+                &self.start_coordinates(),
+                &trip.waypoints,
+                trip.finish.as_ref(),
+            ))
+        } else {
+            let waypoints = trip
+                .waypoints
+                .iter()
+                .chain(trip.finish.iter())
+                .cloned()
+                .collect::<Vec<_>>();
+            Ok(self.shortest_route_through(&self.start_coordinates(), &waypoints))
+        }
+    }
+
+    fn visiting_order(
+        &self,
+        route: &[CoordinatesInTime],
+        waypoints: &[Coordinates],
+    ) -> Vec<Coordinates> {
+        let mut order: Vec<Coordinates> = vec![];
+        for step in route {
+            for waypoint in waypoints {
+                if step.same_as(waypoint) && !order.contains(waypoint) {
+                    order.push(waypoint.clone());
+                }
+            }
+        }
+        order
+    }
+
+    fn is_open(&self, coordinates: &Coordinates) -> bool {
+        coordinates.x < self.width
+            && ((coordinates.y > 0 && coordinates.y < self.height - 1)
+                || self.gaps.contains(coordinates))
+    }
+
+    fn shortest_route(
         &self,
         start: &CoordinatesInTime,
         finish: &Coordinates,
-    ) -> Option<CoordinatesInTime> {
+    ) -> Option<Vec<CoordinatesInTime>> {
+        if start.same_as(finish) {
+            return Some(vec![start.clone()]);
+        }
         let mut queue = VecDeque::new();
         let mut seen = HashSet::new();
+        let mut previous = HashMap::new();
         queue.push_back(start.clone());
-        seen.insert(start.clone());
+        seen.insert(self.normalize(start));
 
         while let Some(current_coordinates) = queue.pop_front() {
             for possible_move in self.possible_moves(&current_coordinates) {
                 if !seen.insert(self.normalize(&possible_move)) {
                     continue;
                 }
+                previous.insert(possible_move.clone(), current_coordinates.clone());
                 if possible_move.same_as(finish) {
-                    return Some(possible_move);
+                    return Some(reconstruct_route(&previous, possible_move));
                 }
                 queue.push_back(possible_move);
             }
@@ -219,11 +703,92 @@ impl Map {
         None
     }
 
+    fn blizzards_at(&self, time: usize) -> HashMap<(usize, usize), Vec<char>> {
+        let horizontal_cycle_time = self.width;
+        let vertical_cycle_time = self.height - 2;
+        let mut blizzards: HashMap<(usize, usize), Vec<char>> = HashMap::new();
+        for (y, winds) in self.winds_per_row.iter().enumerate() {
+            for wind in winds {
+                let x = wind.position_at(time, horizontal_cycle_time);
+                let symbol = if wind.forward { '>' } else { '<' };
+                blizzards.entry((x, y)).or_default().push(symbol);
+            }
+        }
+        for (x, winds) in self.winds_per_column.iter().enumerate() {
+            for wind in winds {
+                let y = wind.position_at(time, vertical_cycle_time) + 1;
+                let symbol = if wind.forward { 'v' } else { '^' };
+                blizzards.entry((x, y)).or_default().push(symbol);
+            }
+        }
+        blizzards
+    }
+
+    fn render_minute(&self, time: usize, expedition: &Coordinates) -> String {
+        let blizzards = self.blizzards_at(time);
+        let mut lines = vec![];
+        for y in 0..self.height {
+            let mut line = String::from("#");
+            for x in 0..self.width {
+                let open = self.is_open(&Coordinates { x, y });
+                line.push(if expedition.same_as(x, y) {
+                    'E'
+                } else if !open {
+                    '#'
+                } else {
+                    match blizzards.get(&(x, y)) {
+                        None => '.',
+                        Some(symbols) if symbols.len() == 1 => symbols[0],
+                        Some(symbols) => char::from_digit(symbols.len() as u32, 10).unwrap_or('*'),
+                    }
+                });
+            }
+            line.push('#');
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+
+    fn render_route(&self, route: &[CoordinatesInTime]) -> String {
+        let mut result = String::new();
+        for (i, step) in route.iter().enumerate() {
+            let title = if i == 0 {
+                "Initial state".to_string()
+            } else {
+                let previous = &route[i - 1];
+                let action = if step.y < previous.y {
+                    "move up"
+                } else if step.y > previous.y {
+                    "move down"
+                } else if step.x < previous.x {
+                    "move left"
+                } else if step.x > previous.x {
+                    "move right"
+                } else {
+                    "wait"
+                };
+                format!("Minute {}, {}", step.time, action)
+            };
+            result.push_str(&format!(
+                "{}:\n{}\n\n",
+                title,
+                self.render_minute(
+                    step.time,
+                    &Coordinates {
+                        x: step.x,
+                        y: step.y
+                    }
+                )
+            ));
+        }
+        result
+    }
+
     fn normalize(&self, coordinates: &CoordinatesInTime) -> CoordinatesInTime {
         CoordinatesInTime {
             x: coordinates.x,
             y: coordinates.y,
-            time: coordinates.time % self.total_cycle_time(),
+            time: coordinates.time % self.total_cycle_time,
         }
     }
 
@@ -257,20 +822,39 @@ impl Map {
     }
 
     fn is_possible_move(&self, coordinates: &CoordinatesInTime) -> bool {
-        self.possible_moves_at_time[coordinates.time % self.total_cycle_time()]
-            [(coordinates.x, coordinates.y)]
+        match &self.occupancy {
+            Occupancy::Precomputed(possible_moves_at_time) => {
+                possible_moves_at_time[coordinates.time % self.total_cycle_time]
+                    [(coordinates.x, coordinates.y)]
+            }
+            Occupancy::Bitmasks(bitmasks) => {
+                if coordinates.y == 0 || coordinates.y == self.height - 1 {
+                    self.gaps
+                        .iter()
+                        .any(|gap| gap.same_as(coordinates.x, coordinates.y))
+                } else {
+                    !bitmasks.is_blizzard(coordinates.x, coordinates.y, coordinates.time)
+                }
+            }
+        }
     }
 
     fn start_coordinates(&self) -> Coordinates {
-        Coordinates { x: 0, y: 0 }
+        self.gaps[0].clone()
     }
 
     fn goal_coordinates(&self) -> Coordinates {
-        Coordinates {
-            x: self.width - 1,
-            y: self.height - 1,
-        }
+        self.gaps[self.gaps.len() - 1].clone()
+    }
+}
+
+fn reconstruct_route<T: Hash + Eq + Clone>(previous: &HashMap<T, T>, last: T) -> Vec<T> {
+    let mut route = vec![last];
+    while let Some(step) = previous.get(&route[route.len() - 1]) {
+        route.push(step.clone());
     }
+    route.reverse();
+    route
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
@@ -326,11 +910,18 @@ impl CoordinatesInTime {
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Debug)]
 struct Coordinates {
     x: usize,
     y: usize,
 }
 
+impl Coordinates {
+    fn same_as(&self, x: usize, y: usize) -> bool {
+        self.x == x && self.y == y
+    }
+}
+
 fn greatest_common_divisor(mut a: usize, mut b: usize) -> usize {
     while b > 0 {
         let t = b;
@@ -346,11 +937,15 @@ fn least_common_multiple(a: usize, b: usize) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use crate::{greatest_common_divisor, least_common_multiple, parse_map, Wind};
+    use aoc2022::random::XorShift;
 
-    #[test]
-    fn example_from_description() {
-        let map = parse_map(
+    use crate::{
+        greatest_common_divisor, least_common_multiple, parse_map, parse_trip_args, random_basin,
+        Coordinates, CoordinatesInTime, Map, OccupancyKind, Trip, Wind,
+    };
+
+    fn example_map() -> Map {
+        parse_map(
             "#.######
 #>>.<^<#
 #.<..<<#
@@ -361,7 +956,12 @@ mod tests {
                 .map(|l| l.chars().collect())
                 .collect(),
         )
-        .unwrap();
+        .unwrap()
+    }
+
+    #[test]
+    fn example_from_description() {
+        let map = example_map();
 
         assert_eq!(
             Some(18),
@@ -394,4 +994,250 @@ mod tests {
             .would_be_at(7, 1, 8)
         )
     }
+
+    #[test]
+    fn test_route_replay() {
+        let map = example_map();
+
+        let route = map
+            .shortest_route_visiting_all(vec![map.goal_coordinates()])
+            .unwrap();
+        assert_eq!(19, route.len());
+        assert!(route.windows(2).all(|w| w[1].time == w[0].time + 1));
+        assert!(route.iter().all(|step| map.is_possible_move(step)));
+
+        let replay = map.render_route(&route);
+        assert!(replay.starts_with(
+            "Initial state:
+#E######
+#>>.<^<#
+#.<..<<#
+#>v.><>#
+#<^v^^>#
+######.#
+"
+        ));
+        assert!(replay.ends_with(
+            "Minute 18, move down:
+#.######
+#>2.<.<#
+#.2v^2<#
+#>..>2>#
+#<....>#
+######E#
+
+"
+        ));
+        assert_eq!(
+            "#.######
+#E>3.<.#
+#<..<<.#
+#>2.22.#
+#>v..^<#
+######.#",
+            map.render_minute(1, &crate::Coordinates { x: 0, y: 1 })
+        );
+
+        let round_trip = map
+            .shortest_route_visiting_all(vec![
+                map.goal_coordinates(),
+                map.start_coordinates(),
+                map.goal_coordinates(),
+            ])
+            .unwrap();
+        assert_eq!(54, round_trip[round_trip.len() - 1].time);
+        assert_eq!(55, round_trip.len());
+    }
+
+    #[test]
+    fn test_bitmask_occupancy_matches_precomputed() {
+        let mut rng = XorShift::new(46);
+        for _ in 0..20 {
+            let width = rng.next_in_range(1..70) as usize;
+            let height = rng.next_in_range(1..12) as usize;
+            let map = parse_map(random_basin(&mut rng, width, height, 0.3)).unwrap();
+            let precomputed = map.with_occupancy(OccupancyKind::Precomputed);
+            let bitmasks = map.with_occupancy(OccupancyKind::Bitmasks);
+            for time in 0..map.total_cycle_time + 3 {
+                for y in 0..map.height {
+                    for x in 0..map.width {
+                        let coordinates = CoordinatesInTime { x, y, time };
+                        assert_eq!(
+                            precomputed.is_possible_move(&coordinates),
+                            bitmasks.is_possible_move(&coordinates),
+                            "{}x{} basin at {:?}",
+                            width,
+                            height,
+                            coordinates
+                        );
+                    }
+                }
+            }
+            assert_eq!(
+                precomputed.shortest_path_time_visiting_all(vec![map.goal_coordinates()]),
+                bitmasks.shortest_path_time_visiting_all(vec![map.goal_coordinates()])
+            );
+        }
+
+        let map = example_map().with_occupancy(OccupancyKind::Bitmasks);
+        assert_eq!(
+            Some(54),
+            map.shortest_path_time_visiting_all(vec![
+                map.goal_coordinates(),
+                map.start_coordinates(),
+                map.goal_coordinates()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_occupancy_kind_for_size() {
+        assert_eq!(
+            OccupancyKind::Precomputed,
+            OccupancyKind::for_size(100, 36, 1700)
+        );
+        assert_eq!(
+            OccupancyKind::Bitmasks,
+            OccupancyKind::for_size(251, 242, 60240)
+        );
+    }
+
+    #[test]
+    fn test_waypoints_in_any_order() {
+        let map = example_map();
+        let waypoints = vec![Coordinates { x: 5, y: 1 }, Coordinates { x: 0, y: 4 }];
+
+        let in_given_order = map
+            .shortest_route_through(&map.start_coordinates(), &waypoints)
+            .unwrap();
+        let in_reverse_order = map
+            .shortest_route_through(
+                &map.start_coordinates(),
+                &[waypoints[1].clone(), waypoints[0].clone()],
+            )
+            .unwrap();
+        let in_any_order = map
+            .shortest_route_in_any_order(&map.start_coordinates(), &waypoints, None)
+            .unwrap();
+        let best_time = in_any_order[in_any_order.len() - 1].time;
+        assert!(best_time <= in_given_order[in_given_order.len() - 1].time);
+        assert!(best_time <= in_reverse_order[in_reverse_order.len() - 1].time);
+        assert!(in_any_order.iter().all(|step| map.is_possible_move(step)));
+        assert_eq!(2, map.visiting_order(&in_any_order, &waypoints).len());
+
+        let trip = Trip {
+            waypoints: vec![map.goal_coordinates(), map.start_coordinates()],
+            any_order: true,
+            finish: Some(map.goal_coordinates()),
+        };
+        let route = map.plan_trip(&trip).unwrap().unwrap();
+        assert_eq!(18, route[route.len() - 1].time);
+        assert_eq!(
+            vec![map.start_coordinates(), map.goal_coordinates()],
+            map.visiting_order(&route, &trip.waypoints)
+        );
+
+        let trip = Trip {
+            waypoints: vec![Coordinates { x: 3, y: 0 }],
+            any_order: false,
+            finish: None,
+        };
+        assert_eq!(
+            Err("Waypoint 4,0 is not inside the basin".to_string()),
+            map.plan_trip(&trip)
+        );
+    }
+
+    #[test]
+    fn test_repeated_waypoints() {
+        let map = example_map();
+        let goal = map.goal_coordinates();
+
+        let once = map.shortest_route_visiting_all(vec![goal.clone()]).unwrap();
+        let twice = map
+            .shortest_route_visiting_all(vec![goal.clone(), goal])
+            .unwrap();
+        assert_eq!(18, twice[twice.len() - 1].time);
+        assert_eq!(once, twice);
+
+        let trip = Trip {
+            waypoints: vec![map.start_coordinates()],
+            any_order: false,
+            finish: None,
+        };
+        let route = map.plan_trip(&trip).unwrap().unwrap();
+        assert_eq!(1, route.len());
+        assert_eq!(0, route[0].time);
+    }
+
+    #[test]
+    fn test_gaps_away_from_corners() {
+        let map = parse_map(
+            "###.####
+#>>.<^<#
+#.<..<<#
+#>v.><>#
+#<^v^^>#
+####.###"
+                .lines()
+                .map(|l| l.chars().collect())
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(Coordinates { x: 2, y: 0 }, map.start_coordinates());
+        assert_eq!(Coordinates { x: 3, y: 5 }, map.goal_coordinates());
+        for kind in [OccupancyKind::Precomputed, OccupancyKind::Bitmasks] {
+            let map = map.with_occupancy(kind);
+            let route = map
+                .shortest_route_visiting_all(vec![map.goal_coordinates()])
+                .unwrap();
+            assert!(route[0].same_as(&map.start_coordinates()));
+            assert!(route[route.len() - 1].same_as(&map.goal_coordinates()));
+            assert!(route.iter().all(|step| map.is_possible_move(step)));
+        }
+
+        assert_eq!(
+            Err("No gaps in the basin walls".to_string()),
+            parse_map(vec!["#####".chars().collect(); 3]).map(|_| ())
+        );
+        assert_eq!(
+            Err("Gap in the side walls at row 1".to_string()),
+            parse_map(vec![
+                "#.###".chars().collect(),
+                "....#".chars().collect(),
+                "###.#".chars().collect()
+            ])
+            .map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_parse_trip_args() {
+        let args = |s: &str| s.split(' ').map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(Ok(None), parse_trip_args(&args("day24")));
+        assert_eq!(
+            Ok(Some(Trip {
+                waypoints: vec![Coordinates { x: 5, y: 1 }, Coordinates { x: 0, y: 4 }],
+                any_order: true,
+                finish: Some(Coordinates { x: 5, y: 5 }),
+            })),
+            parse_trip_args(&[
+                "day24".to_string(),
+                "--waypoints".to_string(),
+                "6,1 1,4".to_string(),
+                "--any-order".to_string(),
+                "--finish".to_string(),
+                "6,5".to_string(),
+            ])
+        );
+        assert_eq!(
+            Err("--any-order and --finish require --waypoints".to_string()),
+            parse_trip_args(&args("day24 --any-order"))
+        );
+        assert_eq!(
+            Err("Coordinates '0,3' are in the side wall".to_string()),
+            parse_trip_args(&args("day24 --waypoints 0,3"))
+        );
+        assert!(parse_trip_args(&args("day24 --waypoints 2-3")).is_err());
+    }
 }
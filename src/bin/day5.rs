@@ -1,35 +1,53 @@
 #[macro_use]
 extern crate lazy_static;
 
+use std::fmt;
+
 use aoc2022::input::read_lines;
 use regex::Regex;
 
 fn main() {
-    let mut crates_lines: Vec<Vec<char>> = vec![];
-    let mut commands: Vec<Command> = vec![];
-    for line in read_lines("inputs/day5.txt") {
-        if line.starts_with("[") {
-            crates_lines.push(line.chars().collect());
-        } else if line.starts_with("move") {
-            commands.push(Command::parse(&line).unwrap());
+    let lines = read_lines("inputs/day5.txt").collect::<Vec<_>>();
+    let (mut crates, commands) = match parse_input(&lines) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            println!("{}", error);
+            return;
         }
-    }
-    let mut crates = State::parse(crates_lines).unwrap();
+    };
     let mut crates_copy = crates.clone();
-    crates.apply(&commands, false);
-    println!(
-        "Part 1: crates on top: {}",
-        crates.crates_on_top_of_stacks()
-    );
-
-    crates_copy.apply(&commands, true);
-    println!(
-        "Part 2: crates on top while retaining order during moves: {}",
-        crates_copy.crates_on_top_of_stacks()
-    )
+    match crates.apply(&commands, false) {
+        Ok(()) => println!(
+            "Part 1: crates on top: {}",
+            crates.crates_on_top_of_stacks()
+        ),
+        Err(error) => println!("Part 1: {}", error),
+    }
+
+    match crates_copy.apply(&commands, true) {
+        Ok(()) => println!(
+            "Part 2: crates on top while retaining order during moves: {}",
+            crates_copy.crates_on_top_of_stacks()
+        ),
+        Err(error) => println!("Part 2: {}", error),
+    }
 }
 
-#[derive(Debug)]
+fn parse_input(lines: &[String]) -> Result<(State, Vec<Command>), String> {
+    let separator = lines
+        .iter()
+        .position(|l| l.trim().is_empty())
+        .ok_or("Missing empty line between the drawing and the moves".to_string())?;
+    let state = State::parse(&lines[..separator])?;
+    let commands = lines[separator + 1..]
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| Command::parse(l))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((state, commands))
+}
+
+#[derive(Debug, PartialEq)]
 struct Command {
     count: usize,
     from: usize,
@@ -37,76 +55,268 @@ struct Command {
 }
 
 impl Command {
-    fn parse(line: &str) -> Option<Command> {
+    fn parse(line: &str) -> Result<Command, String> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"^move (\d+) from (\d) to (\d)$").unwrap();
-        }
-
-        let captures: Vec<_> = RE.captures_iter(line).collect();
-        if captures.len() != 1 {
-            return None;
+            static ref RE: Regex = Regex::new(r"^move (\d+) from (\d+) to (\d+)$").unwrap();
         }
 
-        if captures[0].len() != 4 {
-            return None;
-        }
-        Some(Command {
-            count: captures[0][1].parse::<usize>().unwrap(),
-            from: captures[0][2].parse::<usize>().unwrap(),
-            to: captures[0][3].parse::<usize>().unwrap(),
+        let captures = RE
+            .captures(line)
+            .ok_or(format!("Invalid move: '{}'", line))?;
+        let number = |i: usize| {
+            captures[i]
+                .parse::<usize>()
+                .map_err(|e| format!("Invalid number in move '{}': {}", line, e))
+        };
+        Ok(Command {
+            count: number(1)?,
+            from: number(2)?,
+            to: number(3)?,
         })
     }
 }
 
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from, self.to)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct State {
-    stacks: Vec<Vec<char>>,
+    labels: Vec<usize>,
+    stacks: Vec<Vec<String>>,
 }
 
 impl State {
-    fn parse(crates_rows: Vec<Vec<char>>) -> Option<State> {
-        if crates_rows.len() == 0 {
-            return None;
+    fn parse(drawing: &[String]) -> Result<State, String> {
+        let (labels_row, crates_rows) = drawing
+            .split_last()
+            .ok_or("Empty crates drawing".to_string())?;
+        let mut labels = vec![];
+        let mut label_spans = vec![];
+        for (start, label) in tokens(labels_row) {
+            labels.push(
+                label
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid stack label '{}'", label))?,
+            );
+            label_spans.push(start..start + label.chars().count());
+        }
+        if labels.is_empty() {
+            return Err("Missing stack labels below the crates".to_string());
+        }
+        if let Some(label) = labels
+            .iter()
+            .find(|l| labels.iter().filter(|o| o == l).count() > 1)
+        {
+            return Err(format!("Duplicate stack label {}", label));
         }
-        let crates_count = (crates_rows[0].len() + 1) / 4;
-        let mut stacks: Vec<Vec<char>> = vec![Vec::new(); crates_count];
 
-        for crates_row in crates_rows {
-            for i in 0..crates_count {
-                if crates_row[i * 4 + 1] != ' ' {
-                    stacks[i].push(crates_row[i * 4 + 1]);
+        let mut stacks: Vec<Vec<String>> = vec![Vec::new(); labels.len()];
+        for (row, crates_row) in crates_rows.iter().enumerate().rev() {
+            for (start, token) in tokens(crates_row) {
+                let name = token
+                    .strip_prefix('[')
+                    .and_then(|t| t.strip_suffix(']'))
+                    .filter(|name| !name.is_empty())
+                    .ok_or(format!(
+                        "Invalid crate '{}' in row {}, column {}",
+                        token,
+                        row + 1,
+                        start + 1
+                    ))?;
+                let end = start + token.chars().count();
+                let stacks_below = label_spans
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, span)| span.start < end && start < span.end)
+                    .map(|(stack, _)| stack)
+                    .collect::<Vec<_>>();
+                match stacks_below[..] {
+                    [stack] => stacks[stack].push(name.to_string()),
+                    [] => {
+                        return Err(format!(
+                            "Crate '{}' in row {}, column {} is not above any stack label",
+                            token,
+                            row + 1,
+                            start + 1
+                        ))
+                    }
+                    _ => {
+                        return Err(format!(
+                        "Crate '{}' in row {}, column {} is above stack labels {}, expected one",
+                        token,
+                        row + 1,
+                        start + 1,
+                        stacks_below
+                            .iter()
+                            .map(|stack| labels[*stack].to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                    }
                 }
             }
         }
-        for i in 0..crates_count {
-            stacks[i].reverse()
+
+        Ok(State { labels, stacks })
+    }
+
+    fn stack_index(&self, label: usize) -> Option<usize> {
+        self.labels.iter().position(|l| *l == label)
+    }
+
+    fn apply(&mut self, commands: &[Command], retain_order: bool) -> Result<(), String> {
+        for (i, command) in commands.iter().enumerate() {
+            let error = |message: String| format!("Move {} ({}): {}", i + 1, command, message);
+            let from = self
+                .stack_index(command.from)
+                .ok_or_else(|| error(format!("unknown stack {}", command.from)))?;
+            let to = self
+                .stack_index(command.to)
+                .ok_or_else(|| error(format!("unknown stack {}", command.to)))?;
+            let len_before = self.stacks[from].len();
+            if command.count > len_before {
+                return Err(error(format!(
+                    "stack {} has only {} crate(s)",
+                    command.from, len_before
+                )));
+            }
+
+            let mut to_move = self.stacks[from].split_off(len_before - command.count);
+            if !retain_order {
+                to_move.reverse();
+            }
+            self.stacks[to].append(&mut to_move);
         }
+        Ok(())
+    }
 
-        Some(State { stacks })
+    fn crates_on_top_of_stacks(&self) -> String {
+        let names = self
+            .stacks
+            .iter()
+            .flat_map(|s| s.last().cloned())
+            .collect::<Vec<_>>();
+        if names.iter().all(|name| name.chars().count() == 1) {
+            names.concat()
+        } else {
+            names.join(" ")
+        }
     }
+}
 
-    fn apply(self: &mut Self, commands: &Vec<Command>, retain_order: bool) {
-        for command in commands {
-            let len_before = self.stacks[command.from - 1].len();
-            let mut to_move: Vec<char> = if retain_order {
-                self.stacks[command.from - 1][len_before - command.count..len_before]
-                    .iter()
-                    .cloned()
-                    .collect()
-            } else {
-                self.stacks[command.from - 1]
-                    .iter()
-                    .rev()
-                    .take(command.count)
-                    .cloned()
-                    .collect()
-            };
-            self.stacks[command.to - 1].append(&mut to_move);
-            self.stacks[command.from - 1].truncate(len_before - command.count);
+fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut result = vec![];
+    let mut start = None;
+    for (column, (index, c)) in line.char_indices().enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((column, index)),
+            (Some((start_column, start_index)), true) => {
+                result.push((start_column, &line[start_index..index]));
+                start = None;
+            }
+            _ => {}
         }
     }
+    if let Some((start_column, start_index)) = start {
+        result.push((start_column, &line[start_index..]));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_input, Command, State};
+
+    fn lines(str: &str) -> Vec<String> {
+        str.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn example_from_description() {
+        let (state, commands) = parse_input(&lines(
+            "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2",
+        ))
+        .unwrap();
+
+        let mut crates = state.clone();
+        crates.apply(&commands, false).unwrap();
+        assert_eq!("CMZ", crates.crates_on_top_of_stacks());
+
+        let mut crates = state;
+        crates.apply(&commands, true).unwrap();
+        assert_eq!("MCD", crates.crates_on_top_of_stacks());
+    }
+
+    #[test]
+    fn test_many_stacks_and_long_labels() {
+        let mut state = State::parse(&lines(
+            "                                             [AB]
+[A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [K]  [XYZ]
+ 1   2   3   4   5   6   7   8   9  10  11    12",
+        ))
+        .unwrap();
+        assert_eq!(12, state.stacks.len());
+        assert_eq!(vec!["XYZ".to_string(), "AB".to_string()], state.stacks[11]);
+
+        let commands = vec![
+            Command::parse("move 2 from 12 to 10").unwrap(),
+            Command::parse("move 1 from 10 to 1").unwrap(),
+        ];
+        state.apply(&commands, false).unwrap();
+        assert_eq!("XYZ B C D E F G H I AB K", state.crates_on_top_of_stacks());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Err("Invalid move: 'move 1 from a to 2'".to_string()),
+            Command::parse("move 1 from a to 2")
+        );
+        assert_eq!(
+            Err("Crate '[A]' in row 1, column 9 is not above any stack label".to_string()),
+            State::parse(&lines("        [A]\n 1   2")).map(|_| ())
+        );
+        assert_eq!(
+            Err(
+                "Crate '[ABC]' in row 1, column 2 is above stack labels 1, 2, expected one"
+                    .to_string()
+            ),
+            State::parse(&lines(" [ABC]\n 1   2")).map(|_| ())
+        );
+        assert_eq!(
+            Err("Invalid stack label 'x'".to_string()),
+            State::parse(&lines("[A]\n x")).map(|_| ())
+        );
+        assert_eq!(
+            Err("Missing empty line between the drawing and the moves".to_string()),
+            parse_input(&lines("[A]\n 1")).map(|_| ())
+        );
 
-    fn crates_on_top_of_stacks(self: &Self) -> String {
-        self.stacks.iter().flat_map(|s| s.last()).collect()
+        let mut state = State::parse(&lines("[A]\n[B] [C]\n 1   2")).unwrap();
+        assert_eq!(
+            Err("Move 1 (move 1 from 3 to 1): unknown stack 3".to_string()),
+            state.apply(&[Command::parse("move 1 from 3 to 1").unwrap()], false)
+        );
+        assert_eq!(
+            Err("Move 2 (move 1 from 2 to 1): stack 2 has only 0 crate(s)".to_string()),
+            state.apply(
+                &[
+                    Command::parse("move 1 from 2 to 1").unwrap(),
+                    Command::parse("move 1 from 2 to 1").unwrap()
+                ],
+                false
+            )
+        );
     }
 }
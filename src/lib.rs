@@ -1 +1,5 @@
+pub mod balanced;
 pub mod input;
+pub mod random;
+pub mod rational;
+pub mod snafu;
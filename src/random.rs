@@ -0,0 +1,23 @@
+use std::ops::Range;
+
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        XorShift { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    pub fn next_in_range(&mut self, range: Range<isize>) -> isize {
+        let width = (range.end - range.start) as u64;
+        range.start + (self.next_u64() % width) as isize
+    }
+}
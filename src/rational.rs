@@ -0,0 +1,185 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    pub fn new(numerator: i128, denominator: i128) -> Option<Rational> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator);
+        let (numerator, denominator) = (numerator / divisor, denominator / divisor);
+        if denominator < 0 {
+            Some(Rational {
+                numerator: numerator.checked_neg()?,
+                denominator: denominator.checked_neg()?,
+            })
+        } else {
+            Some(Rational {
+                numerator,
+                denominator,
+            })
+        }
+    }
+
+    pub fn integer(value: i128) -> Rational {
+        Rational {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    pub fn zero() -> Rational {
+        Rational::integer(0)
+    }
+
+    pub fn one() -> Rational {
+        Rational::integer(1)
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.denominator
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    pub fn to_integer(&self) -> Option<i128> {
+        if self.denominator == 1 {
+            Some(self.numerator)
+        } else {
+            None
+        }
+    }
+
+    pub fn checked_neg(self) -> Option<Rational> {
+        Some(Rational {
+            numerator: self.numerator.checked_neg()?,
+            denominator: self.denominator,
+        })
+    }
+
+    pub fn checked_add(self, other: Rational) -> Option<Rational> {
+        let divisor = gcd(self.denominator, other.denominator);
+        let denominator = (self.denominator / divisor).checked_mul(other.denominator)?;
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator / divisor)?
+            .checked_add(other.numerator.checked_mul(self.denominator / divisor)?)?;
+        Rational::new(numerator, denominator)
+    }
+
+    pub fn checked_sub(self, other: Rational) -> Option<Rational> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    pub fn checked_mul(self, other: Rational) -> Option<Rational> {
+        let first_divisor = gcd(self.numerator, other.denominator);
+        let second_divisor = gcd(other.numerator, self.denominator);
+        Rational::new(
+            (self.numerator / first_divisor).checked_mul(other.numerator / second_divisor)?,
+            (self.denominator / second_divisor).checked_mul(other.denominator / first_divisor)?,
+        )
+    }
+
+    pub fn checked_div(self, other: Rational) -> Option<Rational> {
+        self.checked_mul(other.checked_recip()?)
+    }
+
+    pub fn checked_recip(self) -> Option<Rational> {
+        Rational::new(self.denominator, self.numerator)
+    }
+
+    pub fn checked_pow(self, exponent: i32) -> Option<Rational> {
+        let base = if exponent < 0 {
+            self.checked_recip()?
+        } else {
+            self
+        };
+        let exponent = exponent.unsigned_abs();
+        Some(Rational {
+            numerator: base.numerator.checked_pow(exponent)?,
+            denominator: base.denominator.checked_pow(exponent)?,
+        })
+    }
+}
+
+impl From<isize> for Rational {
+    fn from(value: isize) -> Rational {
+        Rational::integer(value as i128)
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    if a == 0 {
+        1
+    } else {
+        a as i128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rational::Rational;
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(Rational::new(1, 2), Rational::new(-3, -6));
+        assert_eq!(Rational::new(-1, 2), Rational::new(2, -4));
+        assert_eq!(Some(Rational::zero()), Rational::new(0, -5));
+        assert_eq!(None, Rational::new(1, 0));
+        assert_eq!("-1/2", Rational::new(2, -4).unwrap().to_string());
+        assert_eq!("7", Rational::integer(7).to_string());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let half = Rational::new(1, 2).unwrap();
+        let third = Rational::new(1, 3).unwrap();
+
+        assert_eq!(Rational::new(5, 6), half.checked_add(third));
+        assert_eq!(Rational::new(1, 6), half.checked_sub(third));
+        assert_eq!(Rational::new(1, 6), half.checked_mul(third));
+        assert_eq!(Rational::new(3, 2), half.checked_div(third));
+        assert_eq!(None, half.checked_div(Rational::zero()));
+        assert_eq!(Rational::new(1, 8), half.checked_pow(3));
+        assert_eq!(Some(Rational::integer(9)), third.checked_pow(-2));
+        assert_eq!(
+            Some(3),
+            half.checked_mul(Rational::integer(6)).unwrap().to_integer()
+        );
+        assert_eq!(None, half.to_integer());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = Rational::integer(i128::MAX);
+        assert_eq!(None, big.checked_add(Rational::one()));
+        assert_eq!(None, big.checked_mul(Rational::integer(2)));
+        assert_eq!(None, Rational::integer(i128::MIN).checked_neg());
+        assert_eq!(None, Rational::integer(10).checked_pow(40));
+    }
+}
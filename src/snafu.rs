@@ -0,0 +1,92 @@
+use crate::balanced::{Balanced, BalancedRadix};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct SnafuRadix;
+
+impl BalancedRadix for SnafuRadix {
+    const NAME: &'static str = "snafu";
+    const BASE: i8 = 5;
+    const ALPHABET: &'static str = "=-012";
+}
+
+pub type Snafu = Balanced<SnafuRadix>;
+
+#[cfg(test)]
+mod tests {
+    use crate::{random::XorShift, snafu::Snafu};
+
+    #[test]
+    fn test_conversions() {
+        for (decimal, snafu) in [
+            (0, "0"),
+            (1, "1"),
+            (2, "2"),
+            (3, "1="),
+            (4, "1-"),
+            (5, "10"),
+            (8, "2="),
+            (10, "20"),
+            (15, "1=0"),
+            (20, "1-0"),
+            (2022, "1=11-2"),
+            (12345, "1-0---0"),
+            (314159265, "1121-1110-1=0"),
+            (-3, "-2"),
+        ] {
+            assert_eq!(snafu, Snafu::from(decimal).to_string());
+            assert_eq!(Ok(Snafu::from(decimal)), snafu.parse::<Snafu>());
+            assert_eq!(Some(decimal), Snafu::from(decimal).to_i128());
+        }
+        assert_eq!(Ok(Snafu::from(3)), "001=".parse::<Snafu>());
+        assert!("".parse::<Snafu>().is_err());
+        assert_eq!(
+            Err("Unexpected char for snafu digit: '3'".to_string()),
+            "1=3".parse::<Snafu>()
+        );
+        for value in [i128::MIN, i128::MAX] {
+            assert_eq!(Some(value), Snafu::from(value).to_i128());
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_against_i128() {
+        let mut rng = XorShift::new(48);
+        for _ in 0..1000 {
+            let a = rng.next_in_range(-1_000_000_000..1_000_000_000) as i128;
+            let b = rng.next_in_range(-1_000_000_000..1_000_000_000) as i128;
+            let (sa, sb) = (Snafu::from(a), Snafu::from(b));
+            assert_eq!(Snafu::from(a + b), sa.clone() + sb.clone());
+            assert_eq!(Snafu::from(a - b), sa.clone() - sb.clone());
+            assert_eq!(Snafu::from(a * b), sa.clone() * sb.clone());
+            assert_eq!(Snafu::from(-a), -sa.clone());
+            assert_eq!(a.cmp(&b), sa.cmp(&sb));
+        }
+        assert_eq!(
+            Snafu::from(4890),
+            [
+                "1=-0-2", "12111", "2=0=", "21", "2=01", "111", "20012", "112", "1=-1=", "1-12",
+                "12", "1=", "122"
+            ]
+            .iter()
+            .map(|s| s.parse::<Snafu>().unwrap())
+            .sum::<Snafu>()
+        );
+    }
+
+    #[test]
+    fn test_arbitrary_length() {
+        let big = "2".repeat(100).parse::<Snafu>().unwrap();
+        assert_eq!(None, big.to_i128());
+        assert_eq!(
+            format!("1{}", "=".repeat(100)),
+            (big.clone() + Snafu::from(1)).to_string()
+        );
+        assert_eq!(Snafu::zero(), big.clone() - big.clone());
+        assert!(big.clone() * big.clone() > big);
+        assert!(-big.clone() < Snafu::from(i128::MIN));
+        assert_eq!(
+            big.clone() * Snafu::from(3) - big.clone(),
+            [&big, &big].into_iter().sum::<Snafu>()
+        );
+    }
+}